@@ -0,0 +1,99 @@
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use std::net::IpAddr;
+
+/// Message returned when a write is attempted on a read-only server
+pub const READONLY_MESSAGE: &str = "The server is in read-only mode; notes can't be modified";
+
+/// Whether the server refuses all writes, set with `NOTEMANCY_READONLY=1`.
+pub fn readonly() -> bool {
+    std::env::var("NOTEMANCY_READONLY")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Message returned when a write request lacks the API key
+pub const UNAUTHORIZED_MESSAGE: &str =
+    "Writes require an 'Authorization: Bearer <key>' header with the server's API key";
+
+/// The key write requests must present as `Authorization: Bearer <key>`, from
+/// `NOTEMANCY_API_KEY`. Writes are unauthenticated when it is unset.
+pub fn api_key() -> Option<String> {
+    std::env::var("NOTEMANCY_API_KEY")
+        .ok()
+        .map(|key| key.trim().to_string())
+        .filter(|key| !key.is_empty())
+}
+
+/// Compares two keys in time independent of where they first differ.
+fn keys_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Checks at startup that writes can't be made anonymously from other machines: without
+/// an API key (or read-only mode) the server only starts on a loopback address, with a
+/// warning.
+pub fn check_write_protection(address: IpAddr) -> Result<(), String> {
+    if readonly() || api_key().is_some() {
+        return Ok(());
+    }
+    if address.is_loopback() {
+        eprintln!("WARNING: NOTEMANCY_API_KEY is not set; write routes are unprotected");
+        Ok(())
+    } else {
+        Err(format!(
+            "NOTEMANCY_API_KEY must be set to listen on {} (or set NOTEMANCY_READONLY=1)",
+            address
+        ))
+    }
+}
+
+/// Request guard for routes that modify the vault or the search index.
+///
+/// Fails with 403 when the server runs in read-only mode, and with 401 when an API key is
+/// configured and the request doesn't carry it.
+pub struct WriteAccess;
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for WriteAccess {
+    type Error = &'static str;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        if readonly() {
+            return Outcome::Error((Status::Forbidden, READONLY_MESSAGE));
+        }
+        let Some(expected) = api_key() else {
+            return Outcome::Success(WriteAccess);
+        };
+        let given = req
+            .headers()
+            .get_one("Authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim);
+        match given {
+            Some(given) if keys_match(given, &expected) => Outcome::Success(WriteAccess),
+            _ => Outcome::Error((Status::Unauthorized, UNAUTHORIZED_MESSAGE)),
+        }
+    }
+}
+
+/// Explains why a request was forbidden.
+#[catch(403)]
+pub fn forbidden() -> &'static str {
+    if readonly() {
+        READONLY_MESSAGE
+    } else {
+        "Forbidden"
+    }
+}
+
+/// Explains why a request was unauthorized.
+#[catch(401)]
+pub fn unauthorized() -> &'static str {
+    UNAUTHORIZED_MESSAGE
+}
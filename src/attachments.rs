@@ -0,0 +1,280 @@
+use rocket::form::{Form, FromForm};
+use rocket::fs::{NamedFile, TempFile};
+use rocket::http::Status;
+use rocket::response::{Redirect, status};
+use rocket::serde::{Deserialize, Serialize, json::Json};
+use serde_json::Value;
+use std::fs;
+
+use crate::access::WriteAccess;
+use crate::vault::Vault;
+use crate::{frontmatter, links, paths, search, utils};
+
+/// Returns the vault-relative folder attachments are saved into.
+///
+/// Configured with `NOTEMANCY_ATTACHMENTS_DIR`, defaulting to "attachments".
+pub fn attachments_dir() -> String {
+    std::env::var("NOTEMANCY_ATTACHMENTS_DIR")
+        .ok()
+        .map(|dir| dir.trim_matches('/').to_string())
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or_else(|| "attachments".to_string())
+}
+
+/// Reduces an uploaded file name to its last path component, rejecting names that are
+/// empty, hidden or refer to a directory.
+pub fn safe_file_name(raw: &str) -> Option<String> {
+    let name = raw.rsplit(['/', '\\']).next()?.trim();
+    if name.is_empty() || name.starts_with('.') {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+/// Returns the path of `target` relative to the folder of the note at `note_relpath`,
+/// as it should be written in a markdown link from that note.
+pub fn link_from(note_relpath: &str, target: &str) -> String {
+    let depth = note_relpath.matches('/').count();
+    format!("{}{}", "../".repeat(depth), target)
+}
+
+/// Points markdown links and images that reference an uploaded file by its original name
+/// (`![](photo.png)` or `![](./photo.png)`) at the saved attachment instead.
+pub fn rewrite_references(body: &str, original: &str, link: &str) -> String {
+    let mut rewritten = body.to_string();
+    for prefix in ["](", "](./"] {
+        for suffix in [")", " "] {
+            rewritten = rewritten.replace(
+                &format!("{}{}{}", prefix, original, suffix),
+                &format!("]({}{}", link, suffix),
+            );
+        }
+    }
+    rewritten
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct MultipartNote {
+    /// The relative path of the note to create
+    pub relpath: String,
+    /// The complete contents of the note
+    pub content: String,
+}
+
+#[derive(FromForm)]
+pub struct MultipartUpload<'r> {
+    /// JSON-encoded `MultipartNote`
+    pub note: String,
+    /// Files referenced by the note, matched by their original file name
+    pub files: Vec<TempFile<'r>>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct MultipartUploaded {
+    /// The relative path of the created note
+    pub relpath: String,
+    /// The vault-relative paths the attached files were saved to
+    pub attachments: Vec<String>,
+}
+
+/// Create a note together with the files it embeds.
+///
+/// The form has a `note` part holding `{ relpath, content }` as JSON and any number of
+/// `files` parts. Files are saved into the attachments folder (renamed with `-2`, `-3`, ...
+/// when taken), and links in the note pointing at a file's original name are rewritten to
+/// the saved path.
+#[post("/notes/upload-multipart", data = "<form>")]
+pub async fn upload_multipart(
+    _write: WriteAccess,
+    vault: Vault,
+    mut form: Form<MultipartUpload<'_>>,
+) -> Result<Json<MultipartUploaded>, status::Custom<String>> {
+    let note: MultipartNote = serde_json::from_str(&form.note).map_err(|e| {
+        status::Custom(
+            Status::UnprocessableEntity,
+            format!("Invalid note part: {}", e),
+        )
+    })?;
+    let note_path = paths::resolve_in_vault(vault.name(), &note.relpath)?;
+    utils::validate_note_extension(&note.relpath)
+        .and_then(|_| utils::validate_text_content(&note.content))
+        .map_err(|e| status::Custom(Status::BadRequest, e))?;
+
+    let vault_dir = paths::vault_root(vault.name())
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    let root = vault_dir.as_path();
+    if note_path.exists() {
+        return Err(status::Custom(
+            Status::Conflict,
+            format!("Note '{}' already exists", note.relpath),
+        ));
+    }
+
+    let folder = attachments_dir();
+    fs::create_dir_all(root.join(&folder))
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    let mut content = note.content.clone();
+    let mut attachments = Vec::new();
+    for file in form.files.iter_mut() {
+        let original = file
+            .raw_name()
+            .and_then(|name| safe_file_name(name.dangerous_unsafe_unsanitized_raw().as_str()))
+            .ok_or_else(|| {
+                status::Custom(
+                    Status::BadRequest,
+                    "Every file part needs a file name".to_string(),
+                )
+            })?;
+        let saved = utils::unique_relpath(root, &format!("{}/{}", folder, original));
+        file.copy_to(root.join(&saved))
+            .await
+            .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+        content = rewrite_references(&content, &original, &link_from(&note.relpath, &saved));
+        attachments.push(saved);
+    }
+
+    if let Some(parent) = note_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    }
+    fs::write(&note_path, &content)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    if let Err(e) = search::reindex_note_async(vault.name(), &note.relpath)
+        .await
+        .map_err(|e| e.to_string())
+    {
+        eprintln!("Failed to index '{}': {}", note.relpath, e);
+    }
+    if search::index_attachments() {
+        let documents: Vec<search::NoteDocument> = attachments
+            .iter()
+            .map(|relpath| search::attachment_document(relpath))
+            .collect();
+        if let Err(e) = search::index_notes_async(vault.name(), &documents)
+            .await
+            .map_err(|e| e.to_string())
+        {
+            eprintln!("Failed to index attachments: {}", e);
+        }
+    }
+
+    Ok(Json(MultipartUploaded {
+        relpath: note.relpath,
+        attachments,
+    }))
+}
+
+/// File extensions treated as images in `![[...]]` embeds
+const IMAGE_EXTENSIONS: [&str; 8] = ["png", "jpg", "jpeg", "gif", "webp", "svg", "avif", "bmp"];
+
+/// Finds the image a note should be represented by, as written in the note: the `cover` or
+/// `image` frontmatter field, or else the first `![alt](src)` image or `![[file.png]]` embed
+/// of the body (outside fenced code blocks).
+pub fn first_image(frontmatter: &Value, body: &str) -> Option<String> {
+    let from_frontmatter = ["cover", "image"].iter().find_map(|key| {
+        frontmatter
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|src| !src.is_empty())
+            .map(str::to_string)
+    });
+    if from_frontmatter.is_some() {
+        return from_frontmatter;
+    }
+
+    let mut in_code_block = false;
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        let mut rest = line;
+        while let Some(start) = rest.find("![") {
+            let after = &rest[start + 2..];
+            if let Some(embed) = after.strip_prefix('[') {
+                let Some(end) = embed.find("]]") else { break };
+                let name = embed[..end].split('|').next().unwrap_or("").trim();
+                let extension = name.rsplit_once('.').map(|(_, ext)| ext.to_lowercase());
+                if extension.is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.as_str())) {
+                    return Some(name.to_string());
+                }
+                rest = &embed[end + 2..];
+                continue;
+            }
+            let Some(src) = after
+                .find("](")
+                .map(|pos| &after[pos + 2..])
+                .and_then(|src| src.find(')').map(|end| src[..end].trim()))
+            else {
+                break;
+            };
+            let src = match src.strip_prefix('<') {
+                Some(quoted) => quoted.split('>').next().unwrap_or(""),
+                None => src.split_whitespace().next().unwrap_or(""),
+            };
+            if !src.is_empty() {
+                return Some(links::percent_decode(src));
+            }
+            rest = after;
+        }
+    }
+    None
+}
+
+/// Resolves an image reference from the note at `note_relpath` to a file in the vault.
+///
+/// The reference is tried relative to the note's folder, then to the vault root, then to
+/// the attachments folder (for embeds written by file name alone).
+pub fn resolve_image(root: &std::path::Path, note_relpath: &str, src: &str) -> Option<String> {
+    let folder = note_relpath
+        .rsplit_once('/')
+        .map(|(folder, _)| folder)
+        .unwrap_or("");
+    [folder.to_string(), String::new(), attachments_dir()]
+        .iter()
+        .filter_map(|base| links::join_relpath(base, src))
+        .find(|relpath| !relpath.is_empty() && root.join(relpath).is_file())
+}
+
+/// An image in the vault, or a redirect to one hosted elsewhere
+#[derive(Responder)]
+pub enum Thumbnail {
+    File(NamedFile),
+    Remote(Redirect),
+}
+
+/// The first image of a note (see `first_image`), for thumbnails. Local images are served
+/// directly and remote ones redirected to; 404 when the note has no image.
+#[get("/notes/first-image?<relpath>")]
+pub async fn note_first_image(
+    vault: Vault,
+    relpath: String,
+) -> Result<Thumbnail, status::Custom<String>> {
+    let note_path = paths::resolve_in_vault(vault.name(), &relpath)?;
+    let raw = fs::read_to_string(&note_path)
+        .map_err(|_| status::Custom(Status::NotFound, format!("Note '{}' not found", relpath)))?;
+    let (frontmatter, body) = frontmatter::parse_frontmatter(&raw);
+    let no_image = || status::Custom(Status::NotFound, format!("Note '{}' has no image", relpath));
+    let src = first_image(&frontmatter, &body).ok_or_else(no_image)?;
+
+    if src.contains("://") {
+        return Ok(Thumbnail::Remote(Redirect::to(src)));
+    }
+    let root = paths::vault_root(vault.name())
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    let image = root.join(resolve_image(&root, &relpath, &src).ok_or_else(no_image)?);
+    // The note's links are not to be trusted any more than a client's relpath.
+    paths::check_within(&root, &image).map_err(|e| status::Custom(Status::BadRequest, e))?;
+    NamedFile::open(image)
+        .await
+        .map(Thumbnail::File)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))
+}
@@ -0,0 +1,73 @@
+use once_cell::sync::Lazy;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Method;
+use rocket::{Request, Response};
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+type Entry = (Instant, Arc<dyn Any + Send + Sync>);
+
+// Vault-wide aggregates keyed by vault name and aggregate name
+static CACHE: Lazy<Mutex<HashMap<(String, &'static str), Entry>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// How long an aggregate stays cached, set with `NOTEMANCY_CACHE_TTL_SECS` (default 30).
+///
+/// Writes made through the server clear the cache immediately; the TTL bounds how long
+/// edits made outside the server can go unnoticed.
+fn ttl() -> Duration {
+    let secs = std::env::var("NOTEMANCY_CACHE_TTL_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(30);
+    Duration::from_secs(secs)
+}
+
+/// Returns the cached value of a vault-wide aggregate, computing it when missing or expired.
+pub fn get_or_compute<T, E, F>(vault_name: &str, key: &'static str, compute: F) -> Result<Arc<T>, E>
+where
+    T: Send + Sync + 'static,
+    F: FnOnce() -> Result<T, E>,
+{
+    let cache_key = (vault_name.to_string(), key);
+    if let Some((computed_at, value)) = CACHE.lock().unwrap().get(&cache_key)
+        && computed_at.elapsed() < ttl()
+        && let Ok(value) = value.clone().downcast::<T>()
+    {
+        return Ok(value);
+    }
+
+    let value = Arc::new(compute()?);
+    CACHE
+        .lock()
+        .unwrap()
+        .insert(cache_key, (Instant::now(), value.clone()));
+    Ok(value)
+}
+
+/// Drops every cached aggregate.
+pub fn invalidate_all() {
+    CACHE.lock().unwrap().clear();
+}
+
+/// Fairing that clears the cache after every successful write request
+pub struct InvalidateOnWrite;
+
+#[rocket::async_trait]
+impl Fairing for InvalidateOnWrite {
+    fn info(&self) -> Info {
+        Info {
+            name: "Invalidate cached aggregates on write",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        let read_only = matches!(req.method(), Method::Get | Method::Head | Method::Options);
+        if !read_only && res.status().class().is_success() {
+            invalidate_all();
+        }
+    }
+}
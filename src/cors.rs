@@ -0,0 +1,69 @@
+use rocket_cors::{AllowedMethods, AllowedOrigins, CorsOptions, Method};
+use std::str::FromStr;
+
+/// Origins allowed to make cross-origin requests, from the comma-separated
+/// `NOTEMANCY_CORS_ORIGINS` (e.g. `https://notes.example.com,http://localhost:5173`), or
+/// any origin when it is `*`. Defaults to `http://localhost:5173`.
+///
+/// Fails naming the first origin that isn't a valid http(s) URL, since a dropped origin only shows
+/// up as confusing CORS errors in the browser.
+pub fn allowed_origins() -> Result<AllowedOrigins, String> {
+    let configured = std::env::var("NOTEMANCY_CORS_ORIGINS").unwrap_or_default();
+    let origins: Vec<&str> = configured
+        .split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .collect();
+    if origins.is_empty() {
+        return Ok(AllowedOrigins::some_exact(&["http://localhost:5173"]));
+    }
+    if origins == ["*"] {
+        return Ok(AllowedOrigins::all());
+    }
+
+    for origin in &origins {
+        let single = CorsOptions {
+            allowed_origins: AllowedOrigins::some_exact(&[origin]),
+            ..Default::default()
+        };
+        let web = origin.starts_with("http://") || origin.starts_with("https://");
+        if !web || single.to_cors().is_err() {
+            return Err(format!("Invalid CORS origin '{}'", origin));
+        }
+    }
+    Ok(AllowedOrigins::some_exact(&origins))
+}
+
+/// Methods allowed in cross-origin requests, from the comma-separated
+/// `CORS_ALLOWED_METHODS` (e.g. `GET,HEAD,OPTIONS` for a read-only instance).
+///
+/// Unknown methods are skipped with a warning. Falls back to rocket_cors' defaults when
+/// unset or when nothing valid is listed.
+pub fn allowed_methods() -> AllowedMethods {
+    let configured = std::env::var("CORS_ALLOWED_METHODS").unwrap_or_default();
+    let methods: AllowedMethods = configured
+        .split(',')
+        .map(str::trim)
+        .filter(|method| !method.is_empty())
+        .filter_map(|method| match Method::from_str(&method.to_uppercase()) {
+            Ok(method) => Some(method),
+            Err(_) => {
+                eprintln!("Ignoring unknown CORS method '{}'", method);
+                None
+            }
+        })
+        .collect();
+    if methods.is_empty() {
+        rocket_cors::CorsOptions::default().allowed_methods
+    } else {
+        methods
+    }
+}
+
+/// Whether cross-origin requests may carry credentials, set with
+/// `CORS_ALLOW_CREDENTIALS=0` or `false` to disable. Defaults to `true`.
+pub fn allow_credentials() -> bool {
+    std::env::var("CORS_ALLOW_CREDENTIALS")
+        .map(|v| !(v == "0" || v.eq_ignore_ascii_case("false")))
+        .unwrap_or(true)
+}
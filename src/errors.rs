@@ -0,0 +1,158 @@
+use rocket::data::{self, Data, FromData};
+use rocket::outcome::Outcome;
+use rocket::request::Request;
+use rocket::serde::json::{self, Json};
+use rocket::serde::{DeserializeOwned, Serialize};
+use std::ops::Deref;
+
+use crate::vault;
+
+/// The reason a request body was rejected, kept for the catchers
+struct RejectedBody(Option<String>);
+
+/// A JSON request body, like `Json<T>`, whose parse error is reported by the 400/422
+/// catchers instead of being discarded.
+pub struct JsonBody<T>(pub T);
+
+impl<T> Deref for JsonBody<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, T: DeserializeOwned + Send> FromData<'r> for JsonBody<T> {
+    type Error = String;
+
+    async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        match <Json<T> as FromData>::from_data(req, data).await {
+            Outcome::Success(body) => Outcome::Success(JsonBody(body.into_inner())),
+            Outcome::Forward(forward) => Outcome::Forward(forward),
+            Outcome::Error((status, e)) => {
+                let detail = match e {
+                    json::Error::Io(e) => e.to_string(),
+                    json::Error::Parse(_, e) => e.to_string(),
+                };
+                req.local_cache(|| RejectedBody(Some(detail.clone())));
+                Outcome::Error((status, detail))
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct BodyError {
+    pub error: &'static str,
+    /// Required fields the body didn't include
+    pub missing: Vec<String>,
+    /// The parser's description of the problem
+    pub detail: String,
+}
+
+/// Pulls the field names out of serde's "missing field `name`" messages.
+fn missing_fields(detail: &str) -> Vec<String> {
+    detail
+        .split("missing field `")
+        .skip(1)
+        .filter_map(|rest| rest.split('`').next())
+        .map(str::to_string)
+        .collect()
+}
+
+/// What the 400/422 catchers respond with
+#[derive(Responder)]
+pub enum Rejection {
+    /// A `JsonBody` that couldn't be parsed, explained field by field
+    Body(Json<BodyError>),
+    /// Any other rejection, such as an unknown vault
+    Message(String),
+}
+
+/// Explains a rejected `JsonBody`, or falls back to `message` when the body wasn't the
+/// problem.
+fn rejection(req: &Request<'_>, message: String) -> Rejection {
+    match &req.local_cache(|| RejectedBody(None)).0 {
+        Some(detail) => Rejection::Body(Json(BodyError {
+            error: "invalid request body",
+            missing: missing_fields(detail),
+            detail: detail.clone(),
+        })),
+        None => Rejection::Message(message),
+    }
+}
+
+/// Explains a body that was valid JSON but didn't match the expected shape.
+#[catch(422)]
+pub fn unprocessable(req: &Request<'_>) -> Rejection {
+    rejection(req, "Unprocessable Entity".to_string())
+}
+
+/// Explains a body that wasn't valid JSON, or names the vault a request asked for that
+/// isn't configured.
+#[catch(400)]
+pub fn bad_request(req: &Request<'_>) -> Rejection {
+    let message = vault::rejection(req).unwrap_or_else(|| "Bad Request".to_string());
+    rejection(req, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vault::Vault;
+    use rocket::http::{ContentType, Status};
+    use rocket::local::blocking::Client;
+    use rocket::serde::Deserialize;
+
+    #[derive(Deserialize)]
+    #[serde(crate = "rocket::serde")]
+    struct Note {
+        content: String,
+    }
+
+    #[post("/body", data = "<note>")]
+    fn body(note: JsonBody<Note>) -> String {
+        note.content.clone()
+    }
+
+    #[get("/vault")]
+    fn vault(vault: Vault) -> String {
+        vault.name().to_string()
+    }
+
+    fn client() -> Client {
+        let rocket = rocket::build()
+            .mount("/", routes![body, vault])
+            .register("/", catchers![bad_request, unprocessable]);
+        Client::tracked(rocket).unwrap()
+    }
+
+    #[test]
+    fn rejected_bodies_list_their_missing_fields() {
+        let client = client();
+        let response = client
+            .post("/body")
+            .header(ContentType::JSON)
+            .body("{}")
+            .dispatch();
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+        let body: serde_json::Value = response.into_json().unwrap();
+        assert_eq!(body["error"], "invalid request body");
+        assert_eq!(body["missing"], serde_json::json!(["content"]));
+    }
+
+    #[test]
+    fn unknown_vaults_are_named() {
+        let client = client();
+        let response = client.get("/vault?vault=nowhere").dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+        let message = response.into_string().unwrap();
+        assert!(
+            message.starts_with("Vault 'nowhere' is not configured"),
+            "{}",
+            message
+        );
+    }
+}
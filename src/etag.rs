@@ -0,0 +1,50 @@
+use rocket::http::Header;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::{self, Responder};
+
+use crate::search;
+
+/// The entity tag of a note: a quoted hash of its raw contents.
+pub fn note_etag(raw: &str) -> String {
+    format!("\"{}\"", search::content_hash(raw))
+}
+
+/// The `If-Match` header of a request, if it has one.
+pub struct IfMatch(pub Option<String>);
+
+impl IfMatch {
+    /// Whether the precondition holds for a resource with the given entity tag.
+    ///
+    /// Holds when there is no header (so older clients keep working), for `*`, or when any
+    /// of the listed tags matches. Weak tags (`W/"..."`) are compared by their value.
+    pub fn allows(&self, etag: &str) -> bool {
+        let Some(header) = &self.0 else {
+            return true;
+        };
+        header.split(',').map(str::trim).any(|candidate| {
+            candidate == "*" || candidate.trim_start_matches("W/") == etag.trim_start_matches("W/")
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IfMatch {
+    type Error = std::convert::Infallible;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(IfMatch(
+            req.headers().get_one("If-Match").map(str::to_string),
+        ))
+    }
+}
+
+/// A response with an `ETag` header
+pub struct Tagged<R>(pub R, pub String);
+
+impl<'r, 'o: 'r, R: Responder<'r, 'o>> Responder<'r, 'o> for Tagged<R> {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'o> {
+        let mut response = self.0.respond_to(req)?;
+        response.set_header(Header::new("ETag", self.1));
+        Ok(response)
+    }
+}
@@ -0,0 +1,275 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use serde_json::Value;
+
+/// Splits off a block opened by a `delimiter` line at the very start of the note (after an
+/// optional UTF-8 BOM) and closed by the next `delimiter` line, which may be the last line
+/// of the file. Lines may end in `\n` or `\r\n`.
+fn split_delimited<'a>(raw: &'a str, delimiter: &str) -> Option<(&'a str, &'a str)> {
+    let raw = raw.strip_prefix('\u{feff}').unwrap_or(raw);
+    let is_delimiter = |line: &str| line.trim_end_matches(['\r', '\n']) == delimiter;
+    let mut lines = raw.split_inclusive('\n');
+    let opening = lines.next()?;
+    if !is_delimiter(opening) || !opening.ends_with('\n') {
+        return None;
+    }
+    let mut end = opening.len();
+    for line in lines {
+        if is_delimiter(line) {
+            return Some((&raw[opening.len()..end], &raw[end + line.len()..]));
+        }
+        end += line.len();
+    }
+    None
+}
+
+/// Splits raw note contents into the YAML frontmatter block and the body.
+///
+/// Frontmatter must open with `---` on the very first line and close with a `---` line;
+/// later `---` lines (horizontal rules) belong to the body. Returns `None` when the note
+/// has no frontmatter block.
+pub fn split(raw: &str) -> Option<(&str, &str)> {
+    split_delimited(raw, "---")
+}
+
+/// Parses a YAML frontmatter block into JSON, falling back to an empty object when it is invalid.
+pub fn yaml_to_json(yaml: &str) -> Value {
+    serde_yaml::from_str::<serde_yaml::Value>(yaml)
+        .ok()
+        .and_then(|yaml| serde_json::to_value(yaml).ok())
+        .unwrap_or_else(|| serde_json::json!({}))
+}
+
+/// Parses raw note contents into its frontmatter (as JSON) and body.
+///
+/// Line endings are normalized to `\n` and a leading BOM dropped. Notes without frontmatter
+/// yield an empty object and the contents.
+pub fn parse_frontmatter(raw: &str) -> (Value, String) {
+    let raw = raw.replace("\r\n", "\n");
+    let raw = raw.strip_prefix('\u{feff}').unwrap_or(&raw);
+    match split(raw) {
+        Some((yaml, body)) => (yaml_to_json(yaml), body.to_string()),
+        None => (serde_json::json!({}), raw.to_string()),
+    }
+}
+
+/// Serializes frontmatter as a YAML block and prepends it to the body.
+pub fn render<T: serde::Serialize>(frontmatter: &T, body: &str) -> Result<String, String> {
+    let yaml = serde_yaml::to_string(frontmatter).map_err(|e| e.to_string())?;
+    Ok(format!("---\n{}---\n{}", yaml, body))
+}
+
+/// Sets one frontmatter key of a note, adding a frontmatter block when there is none.
+/// A `null` value removes the key. Other keys keep their order.
+///
+/// Fails when the existing frontmatter isn't a valid YAML mapping, rather than losing it.
+pub fn set_key(raw: &str, key: &str, value: &Value) -> Result<String, String> {
+    let (mut mapping, body) = match split(raw) {
+        Some((yaml, body)) => {
+            let mapping = match serde_yaml::from_str::<serde_yaml::Value>(yaml) {
+                Ok(serde_yaml::Value::Mapping(mapping)) => mapping,
+                Ok(serde_yaml::Value::Null) => serde_yaml::Mapping::new(),
+                _ => return Err("The note's frontmatter isn't a valid YAML mapping".to_string()),
+            };
+            (mapping, body)
+        }
+        None => (serde_yaml::Mapping::new(), raw),
+    };
+
+    let key = serde_yaml::Value::String(key.to_string());
+    if value.is_null() {
+        mapping.remove(&key);
+    } else {
+        let value = serde_yaml::to_value(value).map_err(|e| e.to_string())?;
+        match mapping.get_mut(&key) {
+            Some(existing) => *existing = value,
+            None => {
+                mapping.insert(key, value);
+            }
+        }
+    }
+    render(&mapping, body)
+}
+
+/// The syntax of a note's frontmatter block
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrontmatterFormat {
+    /// Delimited by `---` lines
+    Yaml,
+    /// Delimited by `+++` lines
+    Toml,
+}
+
+impl FrontmatterFormat {
+    pub fn name(self) -> &'static str {
+        match self {
+            FrontmatterFormat::Yaml => "yaml",
+            FrontmatterFormat::Toml => "toml",
+        }
+    }
+}
+
+/// Whether a string reads as a date: `YYYY-MM-DD`, `YYYY-MM-DD HH:MM[:SS]` or RFC 3339.
+pub fn is_date(text: &str) -> bool {
+    let text = text.trim();
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").is_ok()
+        || NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").is_ok()
+        || NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").is_ok()
+        || DateTime::parse_from_rfc3339(text).is_ok()
+}
+
+/// Names the type of a frontmatter value. Strings that read as dates are `date`.
+pub fn value_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(text) if is_date(text) => "date",
+        Value::String(_) => "string",
+        Value::Array(_) => "list",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Splits off a TOML frontmatter block delimited by `+++` lines.
+pub fn split_toml(raw: &str) -> Option<(&str, &str)> {
+    split_delimited(raw, "+++")
+}
+
+/// Detects the format of a note's frontmatter block, if it has one.
+pub fn detect_format(raw: &str) -> Option<FrontmatterFormat> {
+    if split(raw).is_some() {
+        Some(FrontmatterFormat::Yaml)
+    } else if split_toml(raw).is_some() {
+        Some(FrontmatterFormat::Toml)
+    } else {
+        None
+    }
+}
+
+/// Converts a TOML value to JSON, rendering dates and times as strings.
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(text) => Value::String(text),
+        toml::Value::Integer(number) => Value::from(number),
+        toml::Value::Float(number) => Value::from(number),
+        toml::Value::Boolean(flag) => Value::Bool(flag),
+        toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        toml::Value::Array(items) => Value::Array(items.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, toml_to_json(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// Rewrites a note's TOML frontmatter block as YAML, keeping the body unchanged.
+///
+/// Notes that already use YAML are returned as-is. Fails when the note has no frontmatter
+/// or its TOML doesn't parse.
+pub fn convert_to_yaml(raw: &str) -> Result<String, String> {
+    match detect_format(raw) {
+        Some(FrontmatterFormat::Yaml) => Ok(raw.to_string()),
+        Some(FrontmatterFormat::Toml) => {
+            let (toml, body) = split_toml(raw).unwrap_or_default();
+            let table: toml::Table =
+                toml::from_str(toml).map_err(|e| format!("Invalid TOML frontmatter: {}", e))?;
+            render(&toml_to_json(toml::Value::Table(table)), body)
+        }
+        None => Err("The note has no frontmatter".to_string()),
+    }
+}
+
+/// Frontmatter given to notes created by the server, whichever route creates them
+pub const DEFAULT_TEMPLATE: &str = "---\ntitle: {{title}}\ndate: {{date}}\n---\n";
+
+/// Today's date in the format used by the `{{date}}` placeholder.
+pub fn today() -> String {
+    Local::now().format("%Y-%m-%d").to_string()
+}
+
+/// Expands the `{{title}}` and `{{date}}` placeholders of a frontmatter template. The title
+/// is written as a YAML scalar, quoted when it holds characters such as `:` or `#` that
+/// would otherwise change the frontmatter.
+pub fn expand_template(template: &str, title: &str, date: &str) -> String {
+    template
+        .replace("{{title}}", &yaml_scalar(title))
+        .replace("{{date}}", date)
+}
+
+/// Text as a single-line YAML scalar that parses back to the same string.
+fn yaml_scalar(text: &str) -> String {
+    serde_yaml::to_string(text)
+        .map(|yaml| yaml.trim_end().to_string())
+        .unwrap_or_else(|_| format!("{:?}", text))
+}
+
+/// Renders the default frontmatter block for a new note with the given title.
+pub fn default_frontmatter(title: &str) -> String {
+    expand_template(DEFAULT_TEMPLATE, title, &today())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn template_titles_parse_back_unchanged() {
+        for title in [
+            "Plan",
+            "Plan: draft",
+            "Plan # two",
+            "'quoted",
+            "\"quoted\"",
+            "- list",
+            "true",
+            "",
+        ] {
+            let expanded = expand_template(DEFAULT_TEMPLATE, title, "2024-01-01");
+            let (frontmatter, body) = parse_frontmatter(&expanded);
+            assert_eq!(frontmatter["title"], title, "in {:?}", expanded);
+            assert_eq!(frontmatter["date"], "2024-01-01");
+            assert_eq!(body, "");
+        }
+    }
+
+    #[test]
+    fn crlf_frontmatter_is_split_off() {
+        let (frontmatter, body) = parse_frontmatter("---\r\ntitle: Plan\r\n---\r\nBody\r\n");
+        assert_eq!(frontmatter["title"], "Plan");
+        assert_eq!(body, "Body\n");
+    }
+
+    #[test]
+    fn closing_delimiter_may_end_the_file() {
+        let (frontmatter, body) = parse_frontmatter("---\ntitle: Plan\n---");
+        assert_eq!(frontmatter["title"], "Plan");
+        assert_eq!(body, "");
+    }
+
+    #[test]
+    fn leading_bom_is_dropped() {
+        let (frontmatter, body) = parse_frontmatter("\u{feff}---\ntitle: Plan\n---\nBody\n");
+        assert_eq!(frontmatter["title"], "Plan");
+        assert_eq!(body, "Body\n");
+    }
+
+    #[test]
+    fn notes_without_frontmatter_keep_their_contents() {
+        let (frontmatter, body) = parse_frontmatter("# Plan\n\nBody\n");
+        assert_eq!(frontmatter, serde_json::json!({}));
+        assert_eq!(body, "# Plan\n\nBody\n");
+    }
+
+    #[test]
+    fn horizontal_rules_in_the_body_are_not_delimiters() {
+        let raw = "---\ntitle: Plan\n---\nAbove\n\n---\n\nBelow\n";
+        let (frontmatter, body) = parse_frontmatter(raw);
+        assert_eq!(frontmatter["title"], "Plan");
+        assert_eq!(body, "Above\n\n---\n\nBelow\n");
+
+        // Without frontmatter, a rule further down doesn't open a block.
+        assert_eq!(split("Above\n---\nBelow\n---\n"), None);
+    }
+}
@@ -0,0 +1,137 @@
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::{Deserialize, Serialize, json::Json};
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+use crate::access::WriteAccess;
+use crate::errors::JsonBody;
+use crate::paths;
+use crate::search;
+use crate::vault::Vault;
+
+/// Runs `git` with the vault directory as its working directory.
+fn git(vault_dir: &Path, args: &[&str]) -> Result<Output, String> {
+    Command::new("git")
+        .arg("-C")
+        .arg(vault_dir)
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run git: {}", e))
+}
+
+/// Whether the vault directory is inside a git work tree.
+pub fn is_repository(vault_dir: &Path) -> Result<bool, String> {
+    let output = git(vault_dir, &["rev-parse", "--is-inside-work-tree"])?;
+    Ok(output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "true")
+}
+
+/// Why a file couldn't be read from git history
+#[derive(Debug)]
+pub enum ShowError {
+    /// `rev` doesn't name a commit
+    UnknownRevision,
+    /// The file didn't exist at `rev`
+    MissingFile,
+    /// git itself failed
+    Git(String),
+}
+
+/// Reads a file, relative to the vault directory, as it was at `rev`.
+pub fn show_file(vault_dir: &Path, rev: &str, relpath: &str) -> Result<String, ShowError> {
+    let commit = format!("{}^{{commit}}", rev);
+    let verified =
+        git(vault_dir, &["rev-parse", "--verify", "--quiet", &commit]).map_err(ShowError::Git)?;
+    if !verified.status.success() {
+        return Err(ShowError::UnknownRevision);
+    }
+
+    // `./` makes the path relative to the vault rather than to the repository root.
+    let object = format!("{}:./{}", rev, relpath);
+    let output = git(vault_dir, &["show", &object]).map_err(ShowError::Git)?;
+    if !output.status.success() {
+        return Err(ShowError::MissingFile);
+    }
+    String::from_utf8(output.stdout)
+        .map_err(|_| ShowError::Git(format!("'{}' isn't valid UTF-8 at {}", relpath, rev)))
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RestoreRequest {
+    /// The relative path of the note to restore
+    pub relpath: String,
+    /// The revision to restore from (defaults to `HEAD`)
+    pub rev: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RestoredNote {
+    pub relpath: String,
+    /// The revision the note was restored from
+    pub rev: String,
+    /// The restored contents
+    pub content: String,
+}
+
+/// Overwrite a note with its committed contents at `rev` (`HEAD` by default) and reindex it.
+///
+/// Fails with 400 when the vault isn't a git repository or `rev` is unknown, and with 404
+/// when the note didn't exist at that revision.
+#[post("/notes/restore-git", data = "<req>")]
+pub async fn restore_from_git(
+    _write: WriteAccess,
+    vault: Vault,
+    req: JsonBody<RestoreRequest>,
+) -> Result<Json<RestoredNote>, status::Custom<String>> {
+    let path = paths::resolve_in_vault(vault.name(), &req.relpath)?;
+    let rev = req.rev.as_deref().map(str::trim).unwrap_or("HEAD");
+    if rev.is_empty() || rev.starts_with('-') {
+        return Err(status::Custom(
+            Status::BadRequest,
+            format!("Invalid revision '{}'", rev),
+        ));
+    }
+
+    let vault_dir = paths::vault_root(vault.name())
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    let vault_dir = vault_dir.as_path();
+    if !is_repository(vault_dir).map_err(|e| status::Custom(Status::InternalServerError, e))? {
+        return Err(status::Custom(
+            Status::BadRequest,
+            "The vault isn't a git repository".to_string(),
+        ));
+    }
+
+    let content = show_file(vault_dir, rev, &req.relpath).map_err(|e| match e {
+        ShowError::UnknownRevision => {
+            status::Custom(Status::BadRequest, format!("Unknown revision '{}'", rev))
+        }
+        ShowError::MissingFile => status::Custom(
+            Status::NotFound,
+            format!("Note '{}' didn't exist at {}", req.relpath, rev),
+        ),
+        ShowError::Git(e) => status::Custom(Status::InternalServerError, e),
+    })?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    }
+    fs::write(&path, &content)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    if let Err(e) = search::reindex_note_async(vault.name(), &req.relpath)
+        .await
+        .map_err(|e| e.to_string())
+    {
+        eprintln!("Failed to reindex '{}': {}", req.relpath, e);
+    }
+
+    Ok(Json(RestoredNote {
+        relpath: req.relpath.clone(),
+        rev: rev.to_string(),
+        content,
+    }))
+}
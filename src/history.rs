@@ -0,0 +1,58 @@
+use chrono::Local;
+use once_cell::sync::Lazy;
+use rocket::serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Number of moves remembered, from `NOTEMANCY_MOVE_HISTORY` (default 20)
+pub fn history_size() -> usize {
+    std::env::var("NOTEMANCY_MOVE_HISTORY")
+        .ok()
+        .and_then(|size| size.trim().parse().ok())
+        .filter(|&size| size > 0)
+        .unwrap_or(20)
+}
+
+/// A move or rename made through the API
+#[derive(Serialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct MoveRecord {
+    #[serde(skip)]
+    pub vault: String,
+    pub from: String,
+    pub to: String,
+    /// When the move happened (RFC3339)
+    pub at: String,
+}
+
+/// Recent moves across all vaults, oldest first. Kept in memory, so it starts empty on
+/// every launch.
+static MOVES: Lazy<Mutex<VecDeque<MoveRecord>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+/// Remembers a move, forgetting the oldest one once `history_size` are kept.
+pub fn record_move(vault_name: &str, from: &str, to: &str) {
+    push(MoveRecord {
+        vault: vault_name.to_string(),
+        from: from.to_string(),
+        to: to.to_string(),
+        at: Local::now().to_rfc3339(),
+    });
+}
+
+/// Puts a move back at the end of the history, e.g. when undoing it failed.
+pub fn push(record: MoveRecord) {
+    let mut moves = MOVES.lock().unwrap();
+    moves.push_back(record);
+    while moves.len() > history_size() {
+        moves.pop_front();
+    }
+}
+
+/// Takes the most recent move made in a vault out of the history.
+pub fn pop_last_move(vault_name: &str) -> Option<MoveRecord> {
+    let mut moves = MOVES.lock().unwrap();
+    let index = moves
+        .iter()
+        .rposition(|record| record.vault == vault_name)?;
+    moves.remove(index)
+}
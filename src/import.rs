@@ -0,0 +1,160 @@
+use rocket::data::{Data, Limits, ToByteUnit};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::{Serialize, json::Json};
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+use crate::access::WriteAccess;
+use crate::vault::Vault;
+use crate::{paths, search, utils};
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SkippedEntry {
+    /// The entry's name inside the archive
+    pub entry: String,
+    pub reason: String,
+}
+
+#[derive(Serialize, Default)]
+#[serde(crate = "rocket::serde")]
+pub struct ImportSummary {
+    /// Vault-relative paths of the files written
+    pub imported: Vec<String>,
+    pub skipped: Vec<SkippedEntry>,
+}
+
+/// Writes the notes in a zip archive into the vault at `root`, keeping their paths inside
+/// the archive. Other files are only written when attachments are indexed.
+fn extract(root: &Path, bytes: Vec<u8>) -> Result<ImportSummary, String> {
+    let mut archive =
+        zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("Invalid zip: {}", e))?;
+    let with_attachments = search::index_attachments();
+    let mut summary = ImportSummary::default();
+
+    for index in 0..archive.len() {
+        let mut entry = archive
+            .by_index(index)
+            .map_err(|e| format!("Invalid zip: {}", e))?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        let mut skip = |reason: &str| {
+            summary.skipped.push(SkippedEntry {
+                entry: name.clone(),
+                reason: reason.to_string(),
+            })
+        };
+
+        let relpath = match entry.enclosed_name() {
+            Some(path) => path.to_string_lossy().replace('\\', "/"),
+            None => {
+                skip("path escapes the vault");
+                continue;
+            }
+        };
+        if paths::validate_relpath(&relpath)
+            .and_then(|_| paths::check_within(root, &root.join(&relpath)))
+            .is_err()
+        {
+            skip("path escapes the vault");
+            continue;
+        }
+        let is_note = paths::is_note_file(Path::new(&relpath));
+        if !is_note && !with_attachments {
+            skip("not a note");
+            continue;
+        }
+        let target = root.join(&relpath);
+        if target.exists() {
+            skip("already exists");
+            continue;
+        }
+
+        let mut contents = Vec::new();
+        if let Err(e) = entry.read_to_end(&mut contents) {
+            skip(&e.to_string());
+            continue;
+        }
+        if is_note {
+            let text = match String::from_utf8(contents.clone()) {
+                Ok(text) => text,
+                Err(_) => {
+                    skip("not valid UTF-8");
+                    continue;
+                }
+            };
+            if let Err(e) = utils::validate_text_content(&text) {
+                skip(&e);
+                continue;
+            }
+        }
+
+        let written = match target.parent() {
+            Some(parent) => fs::create_dir_all(parent),
+            None => Ok(()),
+        }
+        .and_then(|_| fs::write(&target, &contents));
+        if let Err(e) = written {
+            skip(&e.to_string());
+            continue;
+        }
+        summary.imported.push(relpath);
+    }
+    Ok(summary)
+}
+
+/// Import the notes in an uploaded zip archive, keeping the folder structure inside it.
+///
+/// Entries that would land outside the vault or on an existing file are skipped, as are
+/// non-note files unless attachments are indexed. Everything imported is indexed in one
+/// batch. The upload size is limited by the "zip" data limit (64 MiB by default).
+#[post("/notes/import-zip", data = "<data>")]
+pub async fn import_zip(
+    _write: WriteAccess,
+    vault: Vault,
+    limits: &Limits,
+    data: Data<'_>,
+) -> Result<Json<ImportSummary>, status::Custom<String>> {
+    let bytes = data
+        .open(limits.get("zip").unwrap_or(64.mebibytes()))
+        .into_bytes()
+        .await
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    if !bytes.is_complete() {
+        return Err(status::Custom(
+            Status::PayloadTooLarge,
+            "Archive exceeds the upload limit".to_string(),
+        ));
+    }
+
+    let vault_dir = paths::vault_root(vault.name())
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    let summary = rocket::tokio::task::spawn_blocking(move || extract(&vault_dir, bytes.value))
+        .await
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?
+        .map_err(|e| status::Custom(Status::UnprocessableEntity, e))?;
+
+    let mut documents = Vec::new();
+    for relpath in &summary.imported {
+        if !paths::is_note_file(Path::new(relpath)) {
+            documents.push(search::attachment_document(relpath));
+            continue;
+        }
+        match search::note_document(vault.name(), relpath).map_err(|e| e.to_string()) {
+            Ok(document) => documents.push(document),
+            Err(e) => eprintln!("Failed to read imported note '{}': {}", relpath, e),
+        }
+    }
+    if let Err(e) = search::index_notes_async(vault.name(), &documents)
+        .await
+        .map_err(|e| e.to_string())
+    {
+        eprintln!("Failed to index imported notes: {}", e);
+    }
+
+    Ok(Json(summary))
+}
@@ -0,0 +1,212 @@
+use rocket::futures::stream::{self, StreamExt};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::{Serialize, json::Json};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::access::WriteAccess;
+use crate::vault::Vault;
+use crate::{frontmatter, utils};
+
+/// When the last check started, to space out checks
+static LAST_RUN: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Whether external links can be checked, set with `NOTEMANCY_LINKCHECK=1`. Off by default
+/// since checking sends requests to every linked site.
+pub fn enabled() -> bool {
+    std::env::var("NOTEMANCY_LINKCHECK")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Reads a positive number of seconds (or requests) from an environment variable.
+fn env_number(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(default)
+}
+
+/// Minimum time between two checks (`NOTEMANCY_LINKCHECK_INTERVAL_SECS`, default 60)
+pub fn min_interval() -> Duration {
+    Duration::from_secs(env_number("NOTEMANCY_LINKCHECK_INTERVAL_SECS", 60))
+}
+
+/// Time allowed for each request (`NOTEMANCY_LINKCHECK_TIMEOUT_SECS`, default 10)
+pub fn timeout() -> Duration {
+    Duration::from_secs(env_number("NOTEMANCY_LINKCHECK_TIMEOUT_SECS", 10))
+}
+
+/// Number of requests in flight at once (`NOTEMANCY_LINKCHECK_CONCURRENCY`, default 4)
+pub fn concurrency() -> usize {
+    env_number("NOTEMANCY_LINKCHECK_CONCURRENCY", 4) as usize
+}
+
+/// Extracts the distinct `http(s)` URLs of a note body, skipping fenced code blocks.
+///
+/// A URL ends at whitespace or a closing bracket or quote; trailing punctuation is dropped.
+pub fn external_links(body: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    let mut in_code_block = false;
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        let mut rest = line;
+        while let Some(start) = [rest.find("http://"), rest.find("https://")]
+            .into_iter()
+            .flatten()
+            .min()
+        {
+            let candidate = &rest[start..];
+            let end = candidate
+                .find(|c: char| c.is_whitespace() || matches!(c, ')' | ']' | '>' | '<' | '"' | '`'))
+                .unwrap_or(candidate.len());
+            let url = candidate[..end].trim_end_matches(['.', ',', ';', ':', '!', '?', '\'']);
+            if url.len() > "https://".len() && !urls.iter().any(|known| known == url) {
+                urls.push(url.to_string());
+            }
+            rest = &candidate[end..];
+        }
+    }
+    urls
+}
+
+/// The outcome of requesting a URL: its HTTP status, or why there was none
+struct Checked {
+    status: Option<u16>,
+    error: Option<String>,
+}
+
+/// Requests a URL with HEAD, falling back to GET for servers that don't allow HEAD.
+async fn check_url(client: &reqwest::Client, url: &str) -> Checked {
+    let response = match client.head(url).send().await {
+        Ok(response) if response.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED => {
+            client.get(url).send().await
+        }
+        other => other,
+    };
+    match response {
+        Ok(response) => Checked {
+            status: Some(response.status().as_u16()),
+            error: None,
+        },
+        Err(e) => Checked {
+            status: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct DeadLink {
+    pub relpath: String,
+    pub url: String,
+    /// The HTTP status, when the server answered
+    pub status: Option<u16>,
+    /// Why the request failed, when there was no answer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Check the external links of every note under `folder` (the whole vault when omitted)
+/// and report those that fail or answer with an error status.
+///
+/// Each distinct URL is requested once. Responds with 404 when link checking is disabled
+/// and 429 when the previous check started less than `min_interval` ago.
+#[post("/notes/check-external-links?<folder>")]
+pub async fn check_external_links(
+    _write: WriteAccess,
+    vault: Vault,
+    folder: Option<String>,
+) -> Result<Json<Vec<DeadLink>>, status::Custom<String>> {
+    if !enabled() {
+        return Err(status::Custom(
+            Status::NotFound,
+            "Link checking is disabled; set NOTEMANCY_LINKCHECK=1 to enable it".to_string(),
+        ));
+    }
+    {
+        let mut last_run = LAST_RUN.lock().unwrap();
+        if let Some(last) = *last_run
+            && last.elapsed() < min_interval()
+        {
+            return Err(status::Custom(
+                Status::TooManyRequests,
+                format!(
+                    "Links were checked less than {}s ago",
+                    min_interval().as_secs()
+                ),
+            ));
+        }
+        *last_run = Some(Instant::now());
+    }
+
+    let prefix = folder
+        .as_deref()
+        .map(|folder| folder.trim_matches('/'))
+        .filter(|folder| !folder.is_empty())
+        .map(|folder| format!("{}/", folder));
+    let relpaths = utils::list_note_relpaths(vault.name())
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    let mut links: Vec<(String, String)> = Vec::new();
+    for relpath in relpaths {
+        if prefix
+            .as_ref()
+            .is_some_and(|prefix| !relpath.starts_with(prefix))
+        {
+            continue;
+        }
+        let Ok(raw) = notemancy_core::crud::read_note(vault.name(), &relpath, true) else {
+            continue;
+        };
+        let (_, body) = frontmatter::parse_frontmatter(&raw);
+        for url in external_links(&body) {
+            links.push((relpath.clone(), url));
+        }
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(timeout())
+        .user_agent(concat!("notemancy-server/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    let mut urls: Vec<String> = links.iter().map(|(_, url)| url.clone()).collect();
+    urls.sort();
+    urls.dedup();
+    let results: HashMap<String, Checked> = stream::iter(urls)
+        .map(|url| {
+            let client = client.clone();
+            async move {
+                let checked = check_url(&client, &url).await;
+                (url, checked)
+            }
+        })
+        .buffer_unordered(concurrency())
+        .collect()
+        .await;
+
+    let dead = links
+        .iter()
+        .filter_map(|(relpath, url)| {
+            let checked = results.get(url)?;
+            let alive = checked.status.is_some_and(|status| status < 400);
+            (!alive).then(|| DeadLink {
+                relpath: relpath.clone(),
+                url: url.clone(),
+                status: checked.status,
+                error: checked.error.clone(),
+            })
+        })
+        .collect();
+    Ok(Json(dead))
+}
@@ -0,0 +1,425 @@
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::{Serialize, json::Json};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::sync::Arc;
+
+use crate::vault::Vault;
+use crate::{cache, utils};
+
+/// A reference to another note, as written in a note's body
+#[derive(Debug, PartialEq)]
+pub enum LinkTarget {
+    /// `[[Title]]`, `[[Title|alias]]` or `[[Title#heading]]`, holding the part before `|`/`#`
+    Wiki(String),
+    /// `[text](path/to/note.md)`, holding the (percent-decoded) path
+    Path(String),
+}
+
+/// A link found in a note, with the line it appears on
+pub struct FoundLink {
+    pub target: LinkTarget,
+    pub context: String,
+}
+
+/// Extracts wikilinks and markdown links from a note body.
+///
+/// Lines inside fenced code blocks are skipped, as are images, external URLs and
+/// same-note anchors.
+pub fn extract_links(body: &str) -> Vec<FoundLink> {
+    let mut links = Vec::new();
+    let mut in_code_block = false;
+
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        for target in scan_line(line) {
+            links.push(FoundLink {
+                target,
+                context: line.trim().to_string(),
+            });
+        }
+    }
+    links
+}
+
+/// Finds the link targets on a single line.
+fn scan_line(line: &str) -> Vec<LinkTarget> {
+    let mut targets = Vec::new();
+
+    // Wikilinks: [[target]], [[target|alias]], [[target#heading]]
+    let mut rest = line;
+    while let Some(start) = rest.find("[[") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("]]") else { break };
+        let inner = &after[..end];
+        let name = inner.split(['|', '#']).next().unwrap_or("").trim();
+        if !name.is_empty() {
+            targets.push(LinkTarget::Wiki(name.to_string()));
+        }
+        rest = &after[end + 2..];
+    }
+
+    // Markdown links: [text](target "optional title"), skipping images
+    let mut rest = line;
+    while let Some(pos) = rest.find("](") {
+        let before = &rest[..pos];
+        let after = &rest[pos + 2..];
+        let Some(end) = after.find(')') else { break };
+        let is_image = before
+            .rfind('[')
+            .is_some_and(|open| before[..open].ends_with('!'));
+        if before.contains('[') && !is_image {
+            let href = after[..end].trim();
+            let href = match href.strip_prefix('<') {
+                Some(quoted) => quoted.split('>').next().unwrap_or(""),
+                None => href.split_whitespace().next().unwrap_or(""),
+            };
+            let href = href.split('#').next().unwrap_or("");
+            let external = href.contains("://") || href.starts_with("mailto:");
+            if !href.is_empty() && !external {
+                targets.push(LinkTarget::Path(percent_decode(href)));
+            }
+        }
+        rest = &after[end + 1..];
+    }
+
+    targets
+}
+
+/// Decodes `%XX` escapes, returning the input unchanged if the result isn't valid UTF-8.
+pub fn percent_decode(text: &str) -> String {
+    let hex_value = |byte: u8| (byte as char).to_digit(16).map(|d| d as u8);
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        // The two digits may end the text, as in `a%20`.
+        if bytes[i] == b'%'
+            && let Some(&[high, low]) = bytes.get(i + 1..i + 3)
+            && let (Some(high), Some(low)) = (hex_value(high), hex_value(low))
+        {
+            decoded.push(high * 16 + low);
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8(decoded).unwrap_or_else(|_| text.to_string())
+}
+
+/// Joins a link path onto a folder, resolving `.` and `..`. Returns `None` when the path
+/// climbs above the vault root.
+pub fn join_relpath(folder: &str, href: &str) -> Option<String> {
+    let mut parts: Vec<&str> = if href.starts_with('/') {
+        Vec::new()
+    } else {
+        folder.split('/').filter(|p| !p.is_empty()).collect()
+    };
+    for component in href.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            other => parts.push(other),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Resolves link targets to note relpaths across a vault.
+pub struct LinkResolver {
+    relpaths: HashSet<String>,
+    by_name: HashMap<String, String>,
+}
+
+impl LinkResolver {
+    /// Builds a resolver from `(relpath, title)` pairs.
+    pub fn new(notes: &[(String, String)]) -> LinkResolver {
+        let mut by_name = HashMap::new();
+        // Titles win over file stems, and earlier notes win over later ones.
+        for (relpath, _) in notes {
+            let stem = relpath.rsplit('/').next().unwrap_or(relpath);
+            let stem = stem.rsplit_once('.').map(|(s, _)| s).unwrap_or(stem);
+            by_name
+                .entry(stem.to_lowercase())
+                .or_insert_with(|| relpath.clone());
+        }
+        for (relpath, title) in notes {
+            if !title.is_empty() {
+                by_name.insert(title.to_lowercase(), relpath.clone());
+            }
+        }
+        for (relpath, _) in notes {
+            by_name.insert(relpath.to_lowercase(), relpath.clone());
+            if let Some((without_ext, _)) = relpath.rsplit_once('.') {
+                by_name.insert(without_ext.to_lowercase(), relpath.clone());
+            }
+        }
+        LinkResolver {
+            relpaths: notes.iter().map(|(relpath, _)| relpath.clone()).collect(),
+            by_name,
+        }
+    }
+
+    /// Resolves a link found in the note at `source` to the relpath of the note it targets.
+    ///
+    /// Wikilinks match titles, file names or paths case-insensitively. Markdown links are
+    /// resolved relative to the source note's folder, then relative to the vault root.
+    pub fn resolve(&self, source: &str, target: &LinkTarget) -> Option<String> {
+        match target {
+            LinkTarget::Wiki(name) => self.by_name.get(&name.to_lowercase()).cloned(),
+            LinkTarget::Path(href) => {
+                let folder = source.rsplit_once('/').map(|(f, _)| f).unwrap_or("");
+                let candidates = [join_relpath(folder, href), join_relpath("", href)];
+                candidates.into_iter().flatten().find_map(|candidate| {
+                    if self.relpaths.contains(&candidate) {
+                        return Some(candidate);
+                    }
+                    // Links may leave off the note extension.
+                    utils::note_extensions()
+                        .iter()
+                        .map(|ext| format!("{}.{}", candidate, ext))
+                        .find(|with_ext| self.relpaths.contains(with_ext))
+                })
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct GraphNode {
+    pub relpath: String,
+    pub title: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct GraphEdge {
+    /// The note containing the link
+    pub source: String,
+    /// The note being linked to
+    pub target: String,
+    /// The line of the source note the link appears on
+    pub context: String,
+}
+
+/// All notes of a vault and the links between them
+pub struct LinkGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// Scans every note in the vault and builds the graph of links between them.
+///
+/// Each (source, target) pair appears once, with the context of its first occurrence.
+/// Links from a note to itself are dropped.
+pub fn build_link_graph(vault_name: &str) -> Result<LinkGraph, Box<dyn Error>> {
+    let notes = utils::load_notes(vault_name)?;
+    let pairs: Vec<(String, String)> = notes
+        .iter()
+        .map(|note| (note.relpath.clone(), note.title.clone()))
+        .collect();
+    let resolver = LinkResolver::new(&pairs);
+
+    let mut edges = Vec::new();
+    let mut seen = HashSet::new();
+    for note in &notes {
+        for link in extract_links(&note.body) {
+            let Some(target) = resolver.resolve(&note.relpath, &link.target) else {
+                continue;
+            };
+            if target != note.relpath && seen.insert((note.relpath.clone(), target.clone())) {
+                edges.push(GraphEdge {
+                    source: note.relpath.clone(),
+                    target,
+                    context: link.context,
+                });
+            }
+        }
+    }
+
+    let nodes = pairs
+        .into_iter()
+        .map(|(relpath, title)| GraphNode { relpath, title })
+        .collect();
+    Ok(LinkGraph { nodes, edges })
+}
+
+/// The vault's link graph, reused across requests until the cache expires or a note is
+/// written (see `cache`).
+pub fn cached_link_graph(vault_name: &str) -> Result<Arc<LinkGraph>, Box<dyn Error>> {
+    cache::get_or_compute(vault_name, "link_graph", || build_link_graph(vault_name))
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Backlink {
+    /// The note containing the link
+    pub relpath: String,
+    pub title: String,
+    /// The line the link appears on
+    pub context: String,
+}
+
+/// The notes linking to `relpath`, ordered by relpath.
+pub fn backlinks(graph: &LinkGraph, relpath: &str) -> Vec<Backlink> {
+    let titles: HashMap<&str, &str> = graph
+        .nodes
+        .iter()
+        .map(|node| (node.relpath.as_str(), node.title.as_str()))
+        .collect();
+    let mut backlinks: Vec<Backlink> = graph
+        .edges
+        .iter()
+        .filter(|edge| edge.target == relpath)
+        .map(|edge| Backlink {
+            relpath: edge.source.clone(),
+            title: titles.get(edge.source.as_str()).unwrap_or(&"").to_string(),
+            context: edge.context.clone(),
+        })
+        .collect();
+    backlinks.sort_by(|a, b| a.relpath.cmp(&b.relpath));
+    backlinks
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct LocalGraphNode {
+    pub relpath: String,
+    pub title: String,
+    /// Number of hops from the requested note
+    pub depth: usize,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct LocalGraph {
+    pub nodes: Vec<LocalGraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// Returns the notes within `depth` hops of `relpath`, following links in both directions,
+/// and the links between them.
+pub fn local_subgraph(graph: &LinkGraph, relpath: &str, depth: usize) -> LocalGraph {
+    let mut neighbours: HashMap<&str, Vec<&str>> = HashMap::new();
+    for edge in &graph.edges {
+        neighbours
+            .entry(edge.source.as_str())
+            .or_default()
+            .push(edge.target.as_str());
+        neighbours
+            .entry(edge.target.as_str())
+            .or_default()
+            .push(edge.source.as_str());
+    }
+
+    // Breadth-first search from the requested note.
+    let mut distances: HashMap<&str, usize> = HashMap::from([(relpath, 0)]);
+    let mut queue = VecDeque::from([relpath]);
+    while let Some(current) = queue.pop_front() {
+        let distance = distances[current];
+        if distance == depth {
+            continue;
+        }
+        for &next in neighbours.get(current).into_iter().flatten() {
+            if !distances.contains_key(next) {
+                distances.insert(next, distance + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    let mut nodes: Vec<LocalGraphNode> = graph
+        .nodes
+        .iter()
+        .filter_map(|node| {
+            distances
+                .get(node.relpath.as_str())
+                .map(|&depth| LocalGraphNode {
+                    relpath: node.relpath.clone(),
+                    title: node.title.clone(),
+                    depth,
+                })
+        })
+        .collect();
+    nodes.sort_by(|a, b| a.depth.cmp(&b.depth).then(a.relpath.cmp(&b.relpath)));
+
+    let edges = graph
+        .edges
+        .iter()
+        .filter(|edge| {
+            distances.contains_key(edge.source.as_str())
+                && distances.contains_key(edge.target.as_str())
+        })
+        .cloned()
+        .collect();
+
+    LocalGraph { nodes, edges }
+}
+
+/// The neighbourhood of a note in the link graph, following links in both directions.
+/// `depth` defaults to 1.
+#[get("/notes/graph/local?<relpath>&<depth>")]
+pub fn local_graph(
+    vault: Vault,
+    relpath: String,
+    depth: Option<usize>,
+) -> Result<Json<LocalGraph>, status::Custom<String>> {
+    let graph = cached_link_graph(vault.name())
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    if !graph.nodes.iter().any(|node| node.relpath == relpath) {
+        return Err(status::Custom(
+            Status::NotFound,
+            format!("Note '{}' not found", relpath),
+        ));
+    }
+    Ok(Json(local_subgraph(&graph, &relpath, depth.unwrap_or(1))))
+}
+
+/// The notes linking to a note, by `[[wikilink]]` (matched case-insensitively against its
+/// title, file name or path) or markdown link. Links from the note to itself don't count.
+#[get("/notes/backlinks?<relpath>")]
+pub fn note_backlinks(
+    vault: Vault,
+    relpath: String,
+) -> Result<Json<Vec<Backlink>>, status::Custom<String>> {
+    let graph = cached_link_graph(vault.name())
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    if !graph.nodes.iter().any(|node| node.relpath == relpath) {
+        return Err(status::Custom(
+            Status::NotFound,
+            format!("Note '{}' not found", relpath),
+        ));
+    }
+    Ok(Json(backlinks(&graph, &relpath)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_are_decoded_anywhere() {
+        assert_eq!(percent_decode("my%20note.md"), "my note.md");
+        assert_eq!(percent_decode("a%20"), "a ");
+        assert_eq!(percent_decode("%C3%A9t%C3%A9.md"), "été.md");
+    }
+
+    #[test]
+    fn incomplete_escapes_are_kept() {
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("a%2"), "a%2");
+        assert_eq!(percent_decode("a%zz.md"), "a%zz.md");
+    }
+}
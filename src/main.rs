@@ -3,23 +3,82 @@ extern crate rocket;
 
 use chrono::{DateTime, Local};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+mod access;
+mod attachments;
+mod cache;
+mod cors;
+mod errors;
+mod etag;
+mod frontmatter;
+mod git;
+mod history;
+mod import;
+mod linkcheck;
+mod links;
+mod metrics;
+mod outline;
+mod paths;
+mod render;
 mod search;
+mod search_log;
+mod shape;
+mod shutdown;
+mod tags;
+mod title;
 mod utils;
+mod vault;
+mod versions;
+mod watch;
 
-use rocket::http::Status;
+use access::WriteAccess;
+use errors::JsonBody;
+use etag::{IfMatch, Tagged};
+use rocket::fairing::AdHoc;
+use rocket::fs::NamedFile;
+use rocket::futures::stream::{self, Stream};
+use rocket::http::{ContentType, Status};
 use rocket::response::status;
+use rocket::response::stream::TextStream;
 use rocket::serde::{Deserialize, Serialize, json::Json};
+use rocket::{Build, Rocket};
 use rocket_cors::AllowedHeaders;
-use rocket_cors::AllowedOrigins;
 use rocket_cors::CorsOptions;
+use vault::Vault;
 
 #[get("/")]
 fn hello() -> &'static str {
     "hello world"
 }
 
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ServerConfig {
+    /// Whether writes are disabled, so clients can hide edit controls
+    pub readonly: bool,
+    /// The vault used when a request doesn't name one
+    pub default_vault: String,
+    /// File extensions recognised as notes
+    pub note_extensions: Vec<String>,
+    /// The folder archived notes are moved into
+    pub archive_dir: String,
+    /// The folder uploaded attachments are saved into
+    pub attachments_dir: String,
+}
+
+/// Server settings relevant to clients.
+#[get("/config")]
+fn server_config() -> Json<ServerConfig> {
+    Json(ServerConfig {
+        readonly: access::readonly(),
+        default_vault: vault::default_vault_name(),
+        note_extensions: utils::note_extensions(),
+        archive_dir: utils::archive_dir(),
+        attachments_dir: attachments::attachments_dir(),
+    })
+}
+
 #[derive(Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct UploadNoteRequest {
@@ -29,61 +88,93 @@ pub struct UploadNoteRequest {
     pub content: String,
 }
 
+/// Create a note from its full contents and add it to the search index.
 #[post("/notes/upload", data = "<note>")]
-fn upload_note(
-    note: Json<UploadNoteRequest>,
+async fn upload_note(
+    _write: WriteAccess,
+    vault: Vault,
+    note: JsonBody<UploadNoteRequest>,
 ) -> Result<rocket::response::status::Custom<&'static str>, rocket::response::status::Custom<String>>
 {
-    let vault_name = "main";
+    let vault_name = vault.name();
     let relpath = note.relpath.clone();
     let content = note.content.clone();
 
-    // Derive the project (folder path) and title (from file name) from the given relpath.
-    let path = std::path::Path::new(&relpath);
-    // If there is a parent directory, use it; otherwise default to empty string.
-    let project = path
-        .parent()
-        .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or_default();
-    // The file stem (without extension) is used as the note title.
-    let title = path
-        .file_stem()
-        .map(|s| s.to_string_lossy().to_string())
-        .unwrap_or_else(|| "".to_string());
-
-    // Use the core function to create the note file (with default frontmatter).
-    if let Err(e) = notemancy_core::crud::create_note(vault_name, &project, &title) {
+    // Only accept files inside the vault with a recognised note extension.
+    let file_path = paths::resolve_in_vault(vault_name, &relpath)?;
+    if let Err(e) = utils::validate_note_extension(&relpath) {
+        return Err(rocket::response::status::Custom(
+            rocket::http::Status::BadRequest,
+            e,
+        ));
+    }
+
+    // Reject binary payloads before anything touches the vault.
+    if let Err(e) = utils::validate_text_content(&content) {
+        return Err(rocket::response::status::Custom(
+            rocket::http::Status::BadRequest,
+            e,
+        ));
+    }
+
+    // Keep the note being replaced, if any, so the upload can be rolled back.
+    versions::save_version_for(vault_name, &relpath)?;
+
+    // The content is written as given, so only its folders need creating.
+    if let Some(parent) = file_path.parent()
+        && let Err(e) = std::fs::create_dir_all(parent)
+    {
         return Err(rocket::response::status::Custom(
             rocket::http::Status::InternalServerError,
             e.to_string(),
         ));
     }
 
-    // Now overwrite the file with the provided content.
-    match notemancy_core::config::get_vault_dir(vault_name) {
-        Ok(vault_dir) => {
-            let file_path = std::path::Path::new(&vault_dir).join(&relpath);
-            match std::fs::write(&file_path, content) {
-                Ok(_) => Ok(rocket::response::status::Custom(
-                    rocket::http::Status::Ok,
-                    "Note uploaded",
-                )),
-                Err(e) => Err(rocket::response::status::Custom(
-                    rocket::http::Status::InternalServerError,
-                    e.to_string(),
-                )),
-            }
-        }
-        Err(e) => Err(rocket::response::status::Custom(
+    // Write the provided content.
+    if let Err(e) = std::fs::write(&file_path, content) {
+        return Err(rocket::response::status::Custom(
             rocket::http::Status::InternalServerError,
             e.to_string(),
-        )),
+        ));
     }
+
+    // The file is the source of truth; an indexing failure is logged, not returned.
+    if let Err(e) = search::reindex_note_async(vault_name, &relpath)
+        .await
+        .map_err(|e| e.to_string())
+    {
+        eprintln!("Failed to update search index for '{}': {}", relpath, e);
+    }
+
+    Ok(rocket::response::status::Custom(
+        rocket::http::Status::Ok,
+        "Note uploaded",
+    ))
 }
 
-#[get("/notes/tree")]
-fn notes_tree() -> Result<Json<Vec<utils::TreeNode>>, rocket::response::status::Custom<String>> {
-    match utils::build_file_tree() {
+/// The vault's notes as a tree. `sort=modified` lists the most recently edited notes (and
+/// folders holding them) first instead of alphabetically with folders first (`sort=name`).
+#[get("/notes/tree?<include_archived>&<sort>")]
+fn notes_tree(
+    vault: Vault,
+    include_archived: Option<bool>,
+    sort: Option<&str>,
+) -> Result<Json<Vec<utils::TreeNode>>, rocket::response::status::Custom<String>> {
+    let sort = match sort {
+        None | Some("name") => utils::TreeSort::Name,
+        Some("modified") => utils::TreeSort::Modified,
+        Some(other) => {
+            return Err(status::Custom(
+                Status::BadRequest,
+                format!("Unsupported sort '{}'", other),
+            ));
+        }
+    };
+    let options = utils::TreeOptions {
+        include_archived: include_archived.unwrap_or(false),
+        sort,
+    };
+    match utils::build_file_tree(vault.name(), &options) {
         Ok(nodes) => Ok(Json(nodes)),
         Err(e) => Err(rocket::response::status::Custom(
             rocket::http::Status::InternalServerError,
@@ -92,6 +183,96 @@ fn notes_tree() -> Result<Json<Vec<utils::TreeNode>>, rocket::response::status::
     }
 }
 
+/// List the folders of the vault (those containing notes or a `.keep` file), without the
+/// notes themselves.
+#[get("/notes/folders")]
+fn notes_folders(vault: Vault) -> Result<Json<Vec<utils::FolderNode>>, status::Custom<String>> {
+    match utils::build_file_tree(vault.name(), &utils::TreeOptions::default()) {
+        Ok(nodes) => Ok(Json(utils::folder_tree(&nodes, ""))),
+        Err(e) => Err(status::Custom(Status::InternalServerError, e.to_string())),
+    }
+}
+
+/// What to put in a new folder so it shows up in the tree
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum FolderPlaceholder {
+    /// A hidden `.keep` file
+    #[default]
+    Keep,
+    /// An `index.md` note titled after the folder
+    Note,
+    /// Nothing; the folder stays hidden from the tree until it holds a note
+    None,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CreateFolderRequest {
+    /// The folder to create, relative to the vault root
+    pub path: String,
+    #[serde(default)]
+    pub placeholder: FolderPlaceholder,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CreatedFolder {
+    /// The folder path relative to the vault root
+    pub path: String,
+    /// The relpath of the placeholder file, if one was created
+    pub placeholder: Option<String>,
+}
+
+/// Create a folder (and any missing parents). Fails with 409 when it already exists.
+#[post("/notes/folder", data = "<req>")]
+async fn create_folder(
+    _write: WriteAccess,
+    vault: Vault,
+    req: JsonBody<CreateFolderRequest>,
+) -> Result<Json<CreatedFolder>, status::Custom<String>> {
+    let path = req.path.trim_matches('/');
+    let dir = paths::resolve_in_vault(vault.name(), path)?;
+    if dir.exists() {
+        return Err(status::Custom(
+            Status::Conflict,
+            format!("'{}' already exists", path),
+        ));
+    }
+    fs::create_dir_all(&dir)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+
+    let placeholder = match req.placeholder {
+        FolderPlaceholder::Keep => Some((utils::KEEP_FILE, String::new())),
+        FolderPlaceholder::Note => {
+            let title = path.rsplit('/').next().unwrap_or(path);
+            Some(("index.md", frontmatter::default_frontmatter(title)))
+        }
+        FolderPlaceholder::None => None,
+    };
+    let placeholder = match placeholder {
+        Some((file_name, content)) => {
+            fs::write(dir.join(file_name), content)
+                .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+            Some(format!("{}/{}", path, file_name))
+        }
+        None => None,
+    };
+    if req.placeholder == FolderPlaceholder::Note
+        && let Some(relpath) = &placeholder
+        && let Err(e) = search::reindex_note_async(vault.name(), relpath)
+            .await
+            .map_err(|e| e.to_string())
+    {
+        eprintln!("Failed to index '{}': {}", relpath, e);
+    }
+
+    Ok(Json(CreatedFolder {
+        path: path.to_string(),
+        placeholder,
+    }))
+}
+
 #[derive(Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct NoteQuery {
@@ -108,16 +289,34 @@ pub struct NoteContent {
     pub frontmatter: serde_json::Value,
     /// The content of the note without the frontmatter.
     pub content: String,
+    /// Whether `content` was cut short by `max_bytes`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// The full size of the content in bytes; only set when it was truncated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_bytes: Option<usize>,
+    /// The notes linking to this one; only set when requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backlinks: Option<Vec<links::Backlink>>,
 }
 
-#[get("/notes/content?<relpath>")]
-fn note_content(relpath: String) -> Result<Json<NoteContent>, status::Custom<String>> {
-    let vault_name = "main";
+/// A note's title, frontmatter and body. The `ETag` header identifies this version of
+/// the note, for use with `If-Match` on `DELETE /notes`.
+///
+/// `max_bytes` caps the size of the body (never splitting a character); a cut body is
+/// marked `truncated`, and the rest can be read from `/notes/raw`.
+/// `with_backlinks=1` adds the notes linking to this one, from the (cached) link graph.
+#[get("/notes/content?<relpath>&<max_bytes>&<with_backlinks>")]
+fn note_content(
+    vault: Vault,
+    relpath: String,
+    max_bytes: Option<usize>,
+    with_backlinks: Option<&str>,
+) -> Result<Tagged<Json<NoteContent>>, status::Custom<String>> {
+    let vault_name = vault.name();
 
     // Determine the full file path using the vault directory and the relative path.
-    let vault_dir = notemancy_core::config::get_vault_dir(vault_name)
-        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
-    let file_path = Path::new(&vault_dir).join(&relpath);
+    let file_path = paths::resolve_in_vault(vault_name, &relpath)?;
 
     // Retrieve file metadata to get the last modified time.
     let metadata = fs::metadata(&file_path)
@@ -132,24 +331,14 @@ fn note_content(relpath: String) -> Result<Json<NoteContent>, status::Custom<Str
     match notemancy_core::crud::read_note(vault_name, &relpath, true) {
         Ok(raw) => {
             // Parse YAML frontmatter if it exists.
-            let (mut frontmatter, content) = if raw.starts_with("---") {
-                if let Some(end_index) = raw.find("\n---\n") {
-                    // Extract the YAML part (skip the initial '---\n' and exclude the closing delimiter).
-                    let fm_str = &raw[4..end_index];
-                    let body = raw[end_index + 5..].to_string();
-                    // Parse the YAML frontmatter and convert it to JSON.
-                    let parsed = serde_yaml::from_str::<serde_yaml::Value>(fm_str)
-                        .map(|yaml| {
-                            serde_json::to_value(yaml).unwrap_or_else(|_| serde_json::json!({}))
-                        })
-                        .unwrap_or_else(|_| serde_json::json!({}));
-                    (parsed, body)
-                } else {
-                    (serde_json::json!({}), raw)
-                }
-            } else {
-                (serde_json::json!({}), raw)
-            };
+            let (mut frontmatter, mut content) = frontmatter::parse_frontmatter(&raw);
+            let title =
+                title::resolve_title(&relpath, &frontmatter, &content, &title::title_order());
+            let total_bytes = content.len();
+            let truncated = max_bytes.is_some_and(|max_bytes| total_bytes > max_bytes);
+            if let Some(max_bytes) = max_bytes {
+                content.truncate(utils::truncate_bytes(&content, max_bytes).len());
+            }
 
             // Insert the last modified time into the frontmatter JSON.
             if let serde_json::Value::Object(ref mut map) = frontmatter {
@@ -158,19 +347,361 @@ fn note_content(relpath: String) -> Result<Json<NoteContent>, status::Custom<Str
                 frontmatter = serde_json::json!({ "last_modified": modified_str });
             }
 
-            // Retrieve the note title as before.
-            let title = notemancy_core::utils::get_title(vault_name, &relpath)
-                .unwrap_or_else(|_| String::new());
-            Ok(Json(NoteContent {
-                title,
-                frontmatter,
-                content,
-            }))
+            let backlinks = if matches!(with_backlinks, Some("1" | "true")) {
+                let graph = links::cached_link_graph(vault_name)
+                    .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+                Some(links::backlinks(&graph, &relpath))
+            } else {
+                None
+            };
+
+            Ok(Tagged(
+                Json(NoteContent {
+                    title,
+                    frontmatter,
+                    content,
+                    truncated,
+                    total_bytes: truncated.then_some(total_bytes),
+                    backlinks,
+                }),
+                etag::note_etag(&raw),
+            ))
         }
         Err(e) => Err(status::Custom(Status::InternalServerError, e.to_string())),
     }
 }
 
+/// The note with the given title, in the same form as `/notes/content`. This is where
+/// rendered wikilinks point.
+///
+/// Titles are matched case-insensitively, falling back to file names and relpaths the way
+/// wikilinks are resolved in the link graph.
+#[get("/notes/by-title?<title>")]
+fn note_by_title(
+    vault: Vault,
+    title: String,
+) -> Result<Tagged<Json<NoteContent>>, status::Custom<String>> {
+    let resolver = cache::get_or_compute(vault.name(), "link_resolver", || {
+        utils::load_notes(vault.name()).map(|notes| {
+            let pairs: Vec<(String, String)> = notes
+                .into_iter()
+                .map(|note| (note.relpath, note.title))
+                .collect();
+            links::LinkResolver::new(&pairs)
+        })
+    })
+    .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+
+    let relpath = resolver
+        .resolve("", &links::LinkTarget::Wiki(title.clone()))
+        .ok_or_else(|| status::Custom(Status::NotFound, format!("No note titled '{}'", title)))?;
+    note_content(vault, relpath, None, None)
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct NoteUnfurl {
+    pub title: String,
+    /// A short plain-text excerpt of the note body
+    pub excerpt: String,
+    pub tags: Vec<String>,
+    /// The last modified time of the note (RFC3339)
+    pub modified: String,
+}
+
+/// A compact preview of a note, for hover cards over links.
+#[get("/notes/unfurl?<relpath>")]
+fn note_unfurl(vault: Vault, relpath: String) -> Result<Json<NoteUnfurl>, status::Custom<String>> {
+    let vault_name = vault.name();
+    let modified = fs::metadata(paths::resolve_in_vault(vault_name, &relpath)?)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| status::Custom(Status::NotFound, e.to_string()))?;
+
+    let raw = notemancy_core::crud::read_note(vault_name, &relpath, true)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    let (frontmatter, body) = frontmatter::parse_frontmatter(&raw);
+    let title = title::resolve_title(&relpath, &frontmatter, &body, &title::title_order());
+
+    Ok(Json(NoteUnfurl {
+        title,
+        excerpt: utils::excerpt(&body, 200),
+        tags: tags::note_tags(&frontmatter),
+        modified: DateTime::<Local>::from(modified).to_rfc3339(),
+    }))
+}
+
+#[derive(Serialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct NoteLength {
+    pub relpath: String,
+    pub title: String,
+    /// Number of words in the note body (frontmatter excluded)
+    pub words: usize,
+}
+
+/// Export the frontmatter of every note as CSV, one row per note.
+///
+/// `fields` is a comma-separated list of frontmatter keys; the `relpath` and `title`
+/// columns always come first. Missing fields are empty and lists are joined with `;`.
+#[get("/notes/export/csv?<fields>")]
+fn export_csv(
+    vault: Vault,
+    fields: Option<String>,
+) -> Result<(ContentType, TextStream<impl Stream<Item = String>>), status::Custom<String>> {
+    let fields: Vec<String> = fields
+        .unwrap_or_default()
+        .split(',')
+        .map(|field| field.trim().to_string())
+        .filter(|field| !field.is_empty())
+        .collect();
+    let notes = utils::load_notes(vault.name())
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+
+    let mut header = vec!["relpath".to_string(), "title".to_string()];
+    header.extend(fields.iter().cloned());
+    let rows = notes.into_iter().map(move |note| {
+        let mut cells = vec![note.relpath, note.title];
+        cells.extend(
+            fields
+                .iter()
+                .map(|field| utils::csv_cell(note.frontmatter.get(field))),
+        );
+        utils::csv_row(&cells)
+    });
+    let lines = std::iter::once(utils::csv_row(&header)).chain(rows);
+    Ok((ContentType::CSV, TextStream::from(stream::iter(lines))))
+}
+
+/// Every note under `folder` (the whole vault when omitted) as a single Markdown document,
+/// in tree order. Each note is introduced by a `# <title>` heading and a `<!-- relpath -->`
+/// comment. Notes are read as the response streams, so memory use doesn't grow with the vault.
+#[get("/notes/concat?<folder>")]
+fn concat_notes(
+    vault: Vault,
+    folder: Option<String>,
+) -> Result<(ContentType, TextStream<impl Stream<Item = String>>), status::Custom<String>> {
+    let tree = utils::build_file_tree(vault.name(), &utils::TreeOptions::default())
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    let mut notes = Vec::new();
+    outline::tree_notes(&tree, &mut notes);
+
+    let prefix = folder
+        .as_deref()
+        .map(|folder| folder.trim_matches('/'))
+        .filter(|folder| !folder.is_empty())
+        .map(|folder| format!("{}/", folder));
+    let notes: Vec<(String, String)> = notes
+        .into_iter()
+        .filter(|(relpath, _)| {
+            prefix
+                .as_ref()
+                .is_none_or(|prefix| relpath.starts_with(prefix))
+        })
+        .map(|(relpath, title)| (relpath.to_string(), title.to_string()))
+        .collect();
+
+    let sections = notes.into_iter().map(move |(relpath, title)| {
+        let body = match notemancy_core::crud::read_note(vault.name(), &relpath, true) {
+            Ok(raw) => frontmatter::parse_frontmatter(&raw).1,
+            Err(e) => {
+                eprintln!("Failed to read '{}' for concatenation: {}", relpath, e);
+                return String::new();
+            }
+        };
+        format!("# {}\n<!-- {} -->\n\n{}\n\n", title, relpath, body.trim())
+    });
+    Ok((
+        ContentType::Markdown,
+        TextStream::from(stream::iter(sections)),
+    ))
+}
+
+/// Groups of notes with the same body (ignoring frontmatter and whitespace differences).
+#[get("/notes/duplicates")]
+fn duplicate_notes(
+    vault: Vault,
+) -> Result<Json<Vec<utils::DuplicateGroup>>, status::Custom<String>> {
+    let groups = cache::get_or_compute(vault.name(), "duplicates", || {
+        utils::load_notes(vault.name()).map(|notes| utils::duplicate_groups(&notes))
+    })
+    .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    Ok(Json(groups.as_ref().clone()))
+}
+
+/// Frontmatter values whose type differs from the one most notes use for that key, e.g.
+/// `tags` written as a string among notes with lists, or a `created` that isn't a date.
+#[get("/notes/frontmatter/issues")]
+fn frontmatter_issues(
+    vault: Vault,
+) -> Result<Json<Vec<utils::FrontmatterIssue>>, status::Custom<String>> {
+    let issues = cache::get_or_compute(vault.name(), "frontmatter_issues", || {
+        utils::load_notes(vault.name()).map(|notes| utils::frontmatter_issues(&notes))
+    })
+    .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    Ok(Json(issues.as_ref().clone()))
+}
+
+/// Bytes used by the vault's files (notes and attachments), per extension, along with the
+/// `largest` biggest files (10 by default).
+#[get("/notes/disk-usage?<largest>")]
+fn disk_usage(
+    vault: Vault,
+    largest: Option<usize>,
+) -> Result<Json<utils::DiskUsage>, status::Custom<String>> {
+    utils::disk_usage(vault.name(), largest.unwrap_or(10))
+        .map(Json)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))
+}
+
+/// Notes whose body word count lies within `min..=max` (both optional), shortest first.
+#[get("/notes/by-length?<min>&<max>")]
+fn notes_by_length(
+    vault: Vault,
+    min: Option<usize>,
+    max: Option<usize>,
+) -> Result<Json<Vec<NoteLength>>, status::Custom<String>> {
+    let min = min.unwrap_or(0);
+    let max = max.unwrap_or(usize::MAX);
+    if min > max {
+        return Err(status::Custom(
+            Status::BadRequest,
+            format!("min ({}) is greater than max ({})", min, max),
+        ));
+    }
+
+    let lengths = cache::get_or_compute(vault.name(), "note_lengths", || {
+        utils::load_notes(vault.name()).map(|notes| {
+            let mut lengths: Vec<NoteLength> = notes
+                .into_iter()
+                .map(|note| NoteLength {
+                    words: utils::word_count(&note.body),
+                    relpath: note.relpath,
+                    title: note.title,
+                })
+                .collect();
+            lengths.sort_by(|a, b| a.words.cmp(&b.words).then(a.relpath.cmp(&b.relpath)));
+            lengths
+        })
+    })
+    .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+
+    Ok(Json(
+        lengths
+            .iter()
+            .filter(|note| (min..=max).contains(&note.words))
+            .cloned()
+            .collect(),
+    ))
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ParseNoteRequest {
+    /// The complete contents of a note, as it would be uploaded
+    pub content: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ParsedNote {
+    /// The parsed YAML frontmatter as a JSON object (empty when absent or invalid).
+    pub frontmatter: serde_json::Value,
+    /// The content of the note without the frontmatter.
+    pub body: String,
+    /// Whether a frontmatter block was found.
+    pub had_frontmatter: bool,
+}
+
+/// Preview how the server splits content into frontmatter and body, without writing anything.
+#[post("/notes/parse", data = "<req>")]
+fn parse_note(req: JsonBody<ParseNoteRequest>) -> Json<ParsedNote> {
+    let had_frontmatter = frontmatter::split(&req.content).is_some();
+    let (frontmatter, body) = frontmatter::parse_frontmatter(&req.content);
+    Json(ParsedNote {
+        frontmatter,
+        body,
+        had_frontmatter,
+    })
+}
+
+/// The raw contents of a note file, frontmatter included.
+///
+/// The file is streamed from disk rather than read into memory, so very large notes are
+/// cheap to serve. Use `/notes/content` for the parsed JSON form.
+#[get("/notes/raw?<relpath>")]
+async fn note_raw(vault: Vault, relpath: String) -> Result<NamedFile, status::Custom<String>> {
+    utils::validate_note_extension(&relpath).map_err(|e| status::Custom(Status::BadRequest, e))?;
+    NamedFile::open(paths::resolve_in_vault(vault.name(), &relpath)?)
+        .await
+        .map_err(|_| status::Custom(Status::NotFound, format!("Note '{}' not found", relpath)))
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct TitlePreviewRequest {
+    /// The complete contents of the note, frontmatter included
+    pub content: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct TitlePreview {
+    /// The frontmatter title or first H1, or null when the content has neither
+    pub title: Option<String>,
+}
+
+/// Resolve the title of unsaved content: its frontmatter title, else its first H1, else
+/// none. The file name is never used, as the content isn't tied to one.
+#[post("/notes/title-preview", data = "<req>")]
+fn title_preview(req: JsonBody<TitlePreviewRequest>) -> Json<TitlePreview> {
+    let (frontmatter, body) = frontmatter::parse_frontmatter(&req.content);
+    Json(TitlePreview {
+        title: title::find_title(None, &frontmatter, &body, &title::CONTENT_TITLE_ORDER),
+    })
+}
+
+/// Find occurrences of the query terms within a single note.
+///
+/// Searches the complete file (frontmatter included) so line numbers match what the editor
+/// shows. This is a local find and doesn't touch MeiliSearch.
+#[get("/notes/search-in?<relpath>&<q>")]
+fn search_in_note(
+    vault: Vault,
+    relpath: String,
+    q: String,
+) -> Result<Json<Vec<utils::TextMatch>>, status::Custom<String>> {
+    paths::resolve_in_vault(vault.name(), &relpath)?;
+    match notemancy_core::crud::read_note(vault.name(), &relpath, true) {
+        Ok(raw) => Ok(Json(utils::find_matches(&raw, &q))),
+        Err(e) => Err(status::Custom(Status::NotFound, e.to_string())),
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct DefaultFrontmatter {
+    /// The frontmatter template with `{{title}}` and `{{date}}` unexpanded
+    pub template: String,
+    /// The template expanded for the given title and today's date
+    pub expanded: String,
+    /// The expanded frontmatter parsed as a JSON object
+    pub frontmatter: serde_json::Value,
+}
+
+/// The frontmatter a new note receives, so clients can mirror the server's defaults.
+/// `title` defaults to "Untitled".
+#[get("/notes/default-frontmatter?<title>")]
+fn default_frontmatter(title: Option<String>) -> Json<DefaultFrontmatter> {
+    let title = title.unwrap_or_else(|| "Untitled".to_string());
+    let expanded = frontmatter::default_frontmatter(&title);
+    let (parsed, _) = frontmatter::parse_frontmatter(&expanded);
+    Json(DefaultFrontmatter {
+        template: frontmatter::DEFAULT_TEMPLATE.to_string(),
+        expanded,
+        frontmatter: parsed,
+    })
+}
+
 #[derive(Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct UpdateNoteRequest {
@@ -181,38 +712,669 @@ pub struct UpdateNoteRequest {
 }
 
 #[post("/notes/update", data = "<note>")]
-fn update_note(
-    note: Json<UpdateNoteRequest>,
+async fn update_note(
+    _write: WriteAccess,
+    vault: Vault,
+    note: JsonBody<UpdateNoteRequest>,
 ) -> Result<rocket::response::status::Custom<&'static str>, rocket::response::status::Custom<String>>
 {
-    // Read default vault from config folder
-    let config_dir = std::env::var("NOTEMANCY_CONF_DIR").map_err(|e| {
-        rocket::response::status::Custom(
-            rocket::http::Status::InternalServerError,
-            format!("Failed to get config directory: {}", e),
-        )
-    })?;
-
-    let default_vault_path = std::path::Path::new(&config_dir).join("default_vault.txt");
-    let vault_name = match std::fs::read_to_string(&default_vault_path) {
-        Ok(content) => content.trim().to_string(),
-        Err(_) => "main".to_string(), // Fall back to "main" if file doesn't exist
-    };
-
+    let vault_name = vault.name();
     let relpath = note.relpath.clone();
     let content = note.content.clone();
 
+    paths::resolve_in_vault(vault_name, &relpath)?;
+    if let Err(e) = utils::validate_note_extension(&relpath) {
+        return Err(rocket::response::status::Custom(
+            rocket::http::Status::BadRequest,
+            e,
+        ));
+    }
+
+    // Reject binary payloads before overwriting the note.
+    if let Err(e) = utils::validate_text_content(&content) {
+        return Err(rocket::response::status::Custom(
+            rocket::http::Status::BadRequest,
+            e,
+        ));
+    }
+
+    versions::save_version_for(vault_name, &relpath)?;
+
     // Call update_note function from notemancy-core
-    match notemancy_core::crud::update_note(&vault_name, &relpath, &content) {
-        Ok(_) => Ok(rocket::response::status::Custom(
-            rocket::http::Status::Ok,
-            "Note updated",
-        )),
-        Err(e) => Err(rocket::response::status::Custom(
+    if let Err(e) = notemancy_core::crud::update_note(vault_name, &relpath, &content) {
+        return Err(rocket::response::status::Custom(
             rocket::http::Status::InternalServerError,
             e.to_string(),
-        )),
+        ));
+    }
+
+    // The file is the source of truth; an indexing failure is logged, not returned.
+    if let Err(e) = search::reindex_note_async(vault_name, &relpath)
+        .await
+        .map_err(|e| e.to_string())
+    {
+        eprintln!("Failed to update search index for '{}': {}", relpath, e);
     }
+
+    Ok(rocket::response::status::Custom(
+        rocket::http::Status::Ok,
+        "Note updated",
+    ))
+}
+
+/// Delete a note and its search document. Folders left empty by the deletion are removed.
+///
+/// With an `If-Match` header (the `ETag` from `/notes/content`), the note is only deleted
+/// if it hasn't changed since; otherwise the response is 412.
+#[delete("/notes?<relpath>")]
+async fn delete_note(
+    _write: WriteAccess,
+    vault: Vault,
+    relpath: String,
+    if_match: IfMatch,
+) -> Result<status::Custom<&'static str>, status::Custom<String>> {
+    paths::resolve_in_vault(vault.name(), &relpath)?;
+    let root = paths::vault_root(vault.name())
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    // The search document is stored under the on-disk case, which can't be looked up once
+    // the file is gone.
+    let relpath = utils::canonical_relpath(&root, &relpath);
+    let raw = notemancy_core::crud::read_note(vault.name(), &relpath, true)
+        .map_err(|_| status::Custom(Status::NotFound, format!("Note '{}' not found", relpath)))?;
+    if !if_match.allows(&etag::note_etag(&raw)) {
+        return Err(status::Custom(
+            Status::PreconditionFailed,
+            format!("Note '{}' has changed", relpath),
+        ));
+    }
+
+    notemancy_core::crud::delete_note(vault.name(), &relpath)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    utils::prune_empty_folders(&root, &relpath);
+    if let Err(e) = search::delete_note_from_index_async(vault.name(), &relpath)
+        .await
+        .map_err(|e| e.to_string())
+    {
+        eprintln!(
+            "Failed to remove '{}' from the search index: {}",
+            relpath, e
+        );
+    }
+    Ok(status::Custom(Status::Ok, "Note deleted"))
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct BatchDeleteRequest {
+    /// The relative paths of the notes to delete
+    pub relpaths: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct BatchDeleteResult {
+    pub relpath: String,
+    pub deleted: bool,
+    /// Why the note couldn't be deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Delete several notes, then remove them from the search index in one go.
+///
+/// Each note is handled on its own, so a missing or invalid relpath only fails its own item.
+#[post("/notes/batch-delete", data = "<req>")]
+async fn batch_delete(
+    _write: WriteAccess,
+    vault: Vault,
+    req: JsonBody<BatchDeleteRequest>,
+) -> Json<Vec<BatchDeleteResult>> {
+    let mut results = Vec::new();
+    let mut deleted = Vec::new();
+    for relpath in &req.relpaths {
+        let outcome = paths::resolve_in_vault(vault.name(), relpath)
+            .map_err(|e| e.1)
+            .and_then(|path| {
+                if path.is_file() {
+                    paths::vault_root(vault.name()).map_err(|e| e.to_string())
+                } else {
+                    Err("Note not found".to_string())
+                }
+            })
+            .and_then(|root| {
+                // Canonicalized while the file exists, to match its search document.
+                let on_disk = utils::canonical_relpath(&root, relpath);
+                notemancy_core::crud::delete_note(vault.name(), &on_disk)
+                    .map(|_| (root, on_disk))
+                    .map_err(|e| e.to_string())
+            });
+        if let Ok((root, on_disk)) = &outcome {
+            utils::prune_empty_folders(root, on_disk);
+            deleted.push(on_disk.clone());
+        }
+        results.push(BatchDeleteResult {
+            relpath: relpath.clone(),
+            deleted: outcome.is_ok(),
+            error: outcome.err(),
+        });
+    }
+
+    if let Err(e) = search::delete_notes_from_index_async(vault.name(), &deleted)
+        .await
+        .map_err(|e| e.to_string())
+    {
+        eprintln!(
+            "Failed to remove deleted notes from the search index: {}",
+            e
+        );
+    }
+    Json(results)
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct UpsertNoteRequest {
+    /// The relative path to the note (e.g. "notes/my-note.md")
+    pub relpath: String,
+    /// The complete contents of the note (which may include custom frontmatter or body)
+    pub content: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct UpsertNoteResponse {
+    /// The relative path of the written note
+    pub relpath: String,
+    /// Whether the note was newly created (false when an existing note was overwritten)
+    pub created: bool,
+}
+
+/// Write a note whether or not it already exists, then index it.
+///
+/// New notes get their parent folders created, and the default frontmatter is prepended
+/// when the content doesn't bring its own. Existing notes are overwritten as-is.
+#[put("/notes", data = "<note>")]
+async fn upsert_note(
+    _write: WriteAccess,
+    vault: Vault,
+    note: JsonBody<UpsertNoteRequest>,
+) -> Result<Json<UpsertNoteResponse>, status::Custom<String>> {
+    let vault_name = vault.name();
+    paths::resolve_in_vault(vault_name, &note.relpath)?;
+    utils::validate_note_extension(&note.relpath)
+        .and_then(|_| utils::validate_text_content(&note.content))
+        .map_err(|e| status::Custom(Status::BadRequest, e))?;
+
+    let vault_dir = paths::vault_root(vault_name)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    let relpath = utils::canonical_relpath(&vault_dir, &note.relpath);
+    let file_path = vault_dir.join(&relpath);
+    let created = !file_path.exists();
+
+    let content = if created && frontmatter::split(&note.content).is_none() {
+        // Title the note after its file name.
+        let title = file_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        format!(
+            "{}{}",
+            frontmatter::default_frontmatter(&title),
+            note.content
+        )
+    } else {
+        note.content.clone()
+    };
+
+    versions::save_version_for(vault_name, &relpath)?;
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    }
+    fs::write(&file_path, content)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+
+    // The file is the source of truth; an indexing failure is logged, not returned.
+    if let Err(e) = search::reindex_note_async(vault_name, &relpath)
+        .await
+        .map_err(|e| e.to_string())
+    {
+        eprintln!("Failed to update search index for '{}': {}", relpath, e);
+    }
+
+    Ok(Json(UpsertNoteResponse { relpath, created }))
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ArchiveRequest {
+    /// The relative path to the note to archive or unarchive
+    pub relpath: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct MovedNote {
+    /// The relative path the note was moved from
+    pub from: String,
+    /// The relative path the note now lives at
+    pub to: String,
+}
+
+/// What to do when the destination of a move or copy already exists
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum OnConflict {
+    /// Fail with 409
+    #[default]
+    Fail,
+    /// Pick a free name by appending `-2`, `-3`, ... to the file stem
+    Rename,
+    /// Replace the existing note
+    Overwrite,
+}
+
+/// Checks the source and destination of a move or copy within the vault.
+///
+/// Returns the source path, the destination relpath to use (which differs from `to` when
+/// `on_conflict` is `Rename`) and whether an existing note will be replaced. Fails with 404
+/// when `from` doesn't exist and 409 when `to` exists and `on_conflict` is `Fail`.
+fn prepare_transfer(
+    vault_dir: &Path,
+    from: &str,
+    to: &str,
+    on_conflict: OnConflict,
+) -> Result<(PathBuf, String, bool), status::Custom<String>> {
+    paths::validate_relpath(from)
+        .and_then(|_| paths::validate_relpath(to))
+        .and_then(|_| paths::check_within(vault_dir, &vault_dir.join(from)))
+        .and_then(|_| paths::check_within(vault_dir, &vault_dir.join(to)))
+        .map_err(|e| status::Custom(Status::BadRequest, e))?;
+    let source = vault_dir.join(from);
+    if !source.is_file() {
+        return Err(status::Custom(
+            Status::NotFound,
+            format!("Note '{}' not found", from),
+        ));
+    }
+
+    let destination = vault_dir.join(to);
+    let (to, replaces) = if !destination.exists() {
+        (to.to_string(), false)
+    } else {
+        match on_conflict {
+            OnConflict::Fail => {
+                return Err(status::Custom(
+                    Status::Conflict,
+                    format!("Note '{}' already exists", to),
+                ));
+            }
+            OnConflict::Rename => (utils::unique_relpath(vault_dir, to), false),
+            OnConflict::Overwrite if destination.is_file() && destination != source => {
+                (to.to_string(), true)
+            }
+            OnConflict::Overwrite => {
+                return Err(status::Custom(
+                    Status::Conflict,
+                    format!("'{}' can't be overwritten", to),
+                ));
+            }
+        }
+    };
+
+    if let Some(parent) = vault_dir.join(&to).parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    }
+    Ok((source, to, replaces))
+}
+
+/// Moves a note file and updates its search document, returning where the note ended up.
+/// The move is remembered so `/notes/undo-last-move` can reverse it.
+async fn move_note(
+    vault_name: &str,
+    from: &str,
+    to: &str,
+    on_conflict: OnConflict,
+) -> Result<Json<MovedNote>, status::Custom<String>> {
+    let moved = relocate_note(vault_name, from, to, on_conflict).await?;
+    history::record_move(vault_name, &moved.from, &moved.to);
+    Ok(moved)
+}
+
+/// Moves a note file and updates its search document without recording the move. Index
+/// failures are logged rather than returned, since the file on disk is the source of truth.
+async fn relocate_note(
+    vault_name: &str,
+    from: &str,
+    to: &str,
+    on_conflict: OnConflict,
+) -> Result<Json<MovedNote>, status::Custom<String>> {
+    let vault_dir = paths::vault_root(vault_name)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    let from = &utils::canonical_relpath(&vault_dir, from);
+    let to = &utils::canonical_relpath(&vault_dir, to);
+    let (source, to, replaces) = prepare_transfer(&vault_dir, from, to, on_conflict)?;
+    fs::rename(&source, vault_dir.join(&to))
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+
+    let reindexed = async {
+        if replaces {
+            search::delete_note_from_index_async(vault_name, &to).await?;
+        }
+        search::move_note_in_index_async(vault_name, from, &to).await
+    };
+    if let Err(e) = reindexed.await.map_err(|e| e.to_string()) {
+        eprintln!("Failed to update search index for '{}': {}", to, e);
+    }
+    Ok(Json(MovedNote {
+        from: from.to_string(),
+        to,
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct TransferRequest {
+    /// The relative path of the note to move or copy
+    pub from: String,
+    /// The relative path to move or copy it to
+    pub to: String,
+    /// What to do when `to` already exists (defaults to `fail`)
+    #[serde(default)]
+    pub on_conflict: OnConflict,
+}
+
+/// Undo the most recent move or rename made in the vault (including archiving), moving the
+/// note back and reindexing it. Responds with the move that was undone, 404 when there is
+/// none left, and 409 when its original location has been taken since.
+#[post("/notes/undo-last-move")]
+async fn undo_last_move(
+    _write: WriteAccess,
+    vault: Vault,
+) -> Result<Json<history::MoveRecord>, status::Custom<String>> {
+    let last = history::pop_last_move(vault.name())
+        .ok_or_else(|| status::Custom(Status::NotFound, "There is no move to undo".to_string()))?;
+    match relocate_note(vault.name(), &last.to, &last.from, OnConflict::Fail).await {
+        Ok(_) => Ok(Json(last)),
+        Err(e) => {
+            // Keep the move so it can be retried once the conflict is resolved.
+            if e.0 == Status::Conflict {
+                history::push(last);
+            }
+            Err(e)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RenameRequest {
+    /// The current relative path of the note
+    pub from: String,
+    /// Its new relative path
+    pub to: String,
+}
+
+/// Rename a note, creating the folders of its new path as needed. Unlike `/notes/move`,
+/// an existing note at `to` is never replaced or worked around: the response is 409. The
+/// note is reindexed under its new path, with its title resolved again.
+#[post("/notes/rename", data = "<req>")]
+async fn rename_note(
+    _write: WriteAccess,
+    vault: Vault,
+    req: JsonBody<RenameRequest>,
+) -> Result<Json<MovedNote>, status::Custom<String>> {
+    utils::validate_note_extension(&req.to).map_err(|e| status::Custom(Status::BadRequest, e))?;
+    move_note(vault.name(), &req.from, &req.to, OnConflict::Fail).await
+}
+
+/// Move or rename a note. The response holds the actual destination, which differs from
+/// the requested one when `on_conflict` is `rename` and the name was taken.
+#[post("/notes/move", data = "<req>")]
+async fn move_note_route(
+    _write: WriteAccess,
+    vault: Vault,
+    req: JsonBody<TransferRequest>,
+) -> Result<Json<MovedNote>, status::Custom<String>> {
+    utils::validate_note_extension(&req.to).map_err(|e| status::Custom(Status::BadRequest, e))?;
+    move_note(vault.name(), &req.from, &req.to, req.on_conflict).await
+}
+
+/// Copy a note. The response holds the actual destination, which differs from the
+/// requested one when `on_conflict` is `rename` and the name was taken.
+#[post("/notes/copy", data = "<req>")]
+async fn copy_note(
+    _write: WriteAccess,
+    vault: Vault,
+    req: JsonBody<TransferRequest>,
+) -> Result<Json<MovedNote>, status::Custom<String>> {
+    utils::validate_note_extension(&req.to).map_err(|e| status::Custom(Status::BadRequest, e))?;
+    let vault_dir = paths::vault_root(vault.name())
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    let (source, to, _) = prepare_transfer(&vault_dir, &req.from, &req.to, req.on_conflict)?;
+    fs::copy(&source, vault_dir.join(&to))
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+
+    // Reindexing replaces any document left by an overwritten note.
+    if let Err(e) = search::reindex_note_async(vault.name(), &to)
+        .await
+        .map_err(|e| e.to_string())
+    {
+        eprintln!("Failed to index copied note '{}': {}", to, e);
+    }
+    Ok(Json(MovedNote {
+        from: req.from.clone(),
+        to,
+    }))
+}
+
+/// Move a note into the archive folder, keeping its folder structure so it can be restored.
+#[post("/notes/archive", data = "<req>")]
+async fn archive_note(
+    _write: WriteAccess,
+    vault: Vault,
+    req: JsonBody<ArchiveRequest>,
+) -> Result<Json<MovedNote>, status::Custom<String>> {
+    let archive = utils::archive_dir();
+    if Path::new(&req.relpath).starts_with(&archive) {
+        return Err(status::Custom(
+            Status::BadRequest,
+            format!("Note '{}' is already archived", req.relpath),
+        ));
+    }
+    let to = format!("{}/{}", archive, req.relpath);
+    move_note(vault.name(), &req.relpath, &to, OnConflict::Fail).await
+}
+
+/// Move an archived note back to its original location.
+#[post("/notes/unarchive", data = "<req>")]
+async fn unarchive_note(
+    _write: WriteAccess,
+    vault: Vault,
+    req: JsonBody<ArchiveRequest>,
+) -> Result<Json<MovedNote>, status::Custom<String>> {
+    let archive = utils::archive_dir();
+    let to = match Path::new(&req.relpath).strip_prefix(&archive) {
+        Ok(original) if !original.as_os_str().is_empty() => original.to_string_lossy().to_string(),
+        _ => {
+            return Err(status::Custom(
+                Status::BadRequest,
+                format!("Note '{}' is not archived", req.relpath),
+            ));
+        }
+    };
+    move_note(vault.name(), &req.relpath, &to, OnConflict::Fail).await
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SetFrontmatterRequest {
+    /// The relative path to the note
+    pub relpath: String,
+    /// The frontmatter key to set
+    pub key: String,
+    /// The new value; `null` removes the key
+    pub value: serde_json::Value,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct UpdatedFrontmatter {
+    pub relpath: String,
+    /// The note's frontmatter after the change
+    pub frontmatter: serde_json::Value,
+}
+
+/// Set (or, with a `null` value, remove) a single frontmatter key of a note and reindex it.
+#[post("/notes/frontmatter/set", data = "<req>")]
+async fn set_frontmatter_key(
+    _write: WriteAccess,
+    vault: Vault,
+    req: JsonBody<SetFrontmatterRequest>,
+) -> Result<Json<UpdatedFrontmatter>, status::Custom<String>> {
+    if req.key.trim().is_empty() {
+        return Err(status::Custom(
+            Status::BadRequest,
+            "key must not be empty".to_string(),
+        ));
+    }
+    let file_path = paths::resolve_in_vault(vault.name(), &req.relpath)?;
+    let raw = fs::read_to_string(&file_path).map_err(|_| {
+        status::Custom(
+            Status::NotFound,
+            format!("Note '{}' not found", req.relpath),
+        )
+    })?;
+
+    let updated = frontmatter::set_key(&raw, &req.key, &req.value)
+        .map_err(|e| status::Custom(Status::BadRequest, e))?;
+    if updated != raw {
+        fs::write(&file_path, &updated)
+            .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+        if let Err(e) = search::reindex_note_async(vault.name(), &req.relpath)
+            .await
+            .map_err(|e| e.to_string())
+        {
+            eprintln!("Failed to reindex '{}': {}", req.relpath, e);
+        }
+    }
+
+    Ok(Json(UpdatedFrontmatter {
+        relpath: req.relpath.clone(),
+        frontmatter: frontmatter::parse_frontmatter(&updated).0,
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ConvertFrontmatterRequest {
+    /// The relative path to the note to convert
+    pub relpath: String,
+    /// The target format; only "yaml" is supported
+    pub to: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ConvertedNote {
+    pub relpath: String,
+    /// The format the frontmatter was in before conversion
+    pub from: &'static str,
+    /// Whether the file was rewritten (false when it was already in the target format)
+    pub converted: bool,
+    /// The full contents of the note after conversion
+    pub content: String,
+}
+
+/// Rewrite a note's frontmatter in another format (TOML `+++` to YAML `---`) and reindex it.
+#[post("/notes/convert-frontmatter", data = "<req>")]
+async fn convert_frontmatter(
+    _write: WriteAccess,
+    vault: Vault,
+    req: JsonBody<ConvertFrontmatterRequest>,
+) -> Result<Json<ConvertedNote>, status::Custom<String>> {
+    if !req.to.eq_ignore_ascii_case("yaml") {
+        return Err(status::Custom(
+            Status::BadRequest,
+            format!("Unsupported frontmatter format '{}'", req.to),
+        ));
+    }
+    let file_path = paths::resolve_in_vault(vault.name(), &req.relpath)?;
+    let raw = fs::read_to_string(&file_path).map_err(|_| {
+        status::Custom(
+            Status::NotFound,
+            format!("Note '{}' not found", req.relpath),
+        )
+    })?;
+
+    let from = frontmatter::detect_format(&raw)
+        .ok_or_else(|| {
+            status::Custom(
+                Status::BadRequest,
+                format!("Note '{}' has no frontmatter", req.relpath),
+            )
+        })?
+        .name();
+    let content =
+        frontmatter::convert_to_yaml(&raw).map_err(|e| status::Custom(Status::BadRequest, e))?;
+    let converted = content != raw;
+    if converted {
+        fs::write(&file_path, &content)
+            .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+        if let Err(e) = search::reindex_note_async(vault.name(), &req.relpath)
+            .await
+            .map_err(|e| e.to_string())
+        {
+            eprintln!("Failed to reindex '{}': {}", req.relpath, e);
+        }
+    }
+
+    Ok(Json(ConvertedNote {
+        relpath: req.relpath.clone(),
+        from,
+        converted,
+        content,
+    }))
+}
+
+/// Rebuild the vault's search index, re-reading index settings such as synonyms. Only the
+/// default vault is indexed at startup, so other vaults are indexed by calling this once.
+#[post("/notes/reindex")]
+async fn reindex_notes(
+    _write: WriteAccess,
+    vault: Vault,
+) -> Result<status::Custom<&'static str>, status::Custom<String>> {
+    match search::build_search_index_async(vault.name())
+        .await
+        .map_err(|e| e.to_string())
+    {
+        Ok(_) => Ok(status::Custom(Status::Ok, "Search index rebuilt")),
+        Err(e) => Err(status::Custom(Status::InternalServerError, e)),
+    }
+}
+
+/// Bring the search index in line with the vault without rebuilding it, reporting the
+/// notes that were added, deleted and updated, and those that couldn't be read.
+#[post("/notes/index/repair")]
+async fn repair_index(
+    _write: WriteAccess,
+    vault: Vault,
+) -> Result<Json<search::RepairReport>, status::Custom<String>> {
+    search::repair_index_async(vault.name())
+        .await
+        .map(Json)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))
+}
+
+/// Document count, indexing state and field distribution of the search index, with the
+/// number of notes on disk and whether the two have drifted apart.
+#[get("/notes/index/stats")]
+async fn index_stats(vault: Vault) -> Result<Json<search::IndexHealth>, status::Custom<String>> {
+    search::index_health_async(vault.name())
+        .await
+        .map(Json)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))
 }
 
 #[derive(Deserialize)]
@@ -225,50 +1387,346 @@ pub struct SearchQuery {
 #[derive(Serialize)]
 #[serde(crate = "rocket::serde")]
 pub struct SearchResponse {
-    /// Number of search results
+    /// Number of search results in this page
     pub total: usize,
+    /// MeiliSearch's estimate of all matches, for paging with `limit` and `offset`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_total_hits: Option<usize>,
     /// The search results
     pub results: Vec<search::NoteDocument>,
 }
 
-#[get("/notes/search?<query>")]
-async fn search_notes(query: String) -> Result<Json<SearchResponse>, status::Custom<String>> {
-    match search::search_notes_async(&query).await {
-        Ok(result) => Ok(Json(SearchResponse {
-            total: result.hits_count,
-            results: result.hits,
-        })),
-        Err(e) => Err(status::Custom(Status::InternalServerError, e.to_string())),
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SearchGroup {
+    /// The folder the results live in ("" for the vault root)
+    pub folder: String,
+    /// The results in this folder, in relevance order
+    pub results: Vec<search::NoteDocument>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GroupedSearchResponse {
+    /// Number of search results in this page, across all groups
+    pub total: usize,
+    /// MeiliSearch's estimate of all matches, for paging with `limit` and `offset`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_total_hits: Option<usize>,
+    /// The results grouped by folder, ordered by each folder's best match
+    pub groups: Vec<SearchGroup>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde", untagged)]
+pub enum SearchOutput {
+    Flat(SearchResponse),
+    Grouped(GroupedSearchResponse),
+}
+
+/// Search notes.
+///
+/// The query is forwarded to MeiliSearch untouched, so its negative operator works:
+/// `meeting -cancelled` matches notes about meetings that don't mention "cancelled".
+/// `exclude` is a comma-separated list of terms added to the query as negative phrases, so
+/// notes containing any of them are dropped before paging and don't count towards
+/// `estimated_total_hits`. Negative phrases need MeiliSearch 1.2 or later. Hits whose title
+/// or content contains an excluded term are also filtered out here, so older servers never
+/// return them, though their pages can come back short and their counts include them.
+/// `group_by=folder` nests results under their immediate folder and `group_by=top_folder`
+/// under their top-level folder.
+/// Each hit has a `snippet` of its content cropped around the match; `crop_marker`
+/// overrides the marker placed where text was cut (see `search::default_crop_marker`).
+/// `snippets` holds a separate snippet for each field (title, content) the query matched.
+/// With `NOTEMANCY_INDEX_ATTACHMENTS=1`, attachment file names match too; those hits have
+/// `kind: "attachment"` and no content.
+/// `with_lines=1` sets each note hit's `first_match_line`, the line of the note file (counting
+/// frontmatter) where a query term first appears, for jumping to it in an editor.
+/// `limit` (default 20, at most 100) and `offset` page through the matches; the response's
+/// `estimated_total_hits` counts them all.
+/// `tag` (repeatable) keeps only notes whose frontmatter has every given tag.
+#[get(
+    "/notes/search?<query>&<exclude>&<group_by>&<crop_marker>&<with_lines>&<limit>&<offset>&<tag>"
+)]
+#[allow(clippy::too_many_arguments)]
+async fn search_notes(
+    vault: Vault,
+    query: String,
+    exclude: Option<String>,
+    group_by: Option<String>,
+    crop_marker: Option<String>,
+    with_lines: Option<&str>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    tag: Vec<String>,
+) -> Result<Json<SearchOutput>, status::Custom<String>> {
+    let top_level = match group_by.as_deref() {
+        None => None,
+        Some("folder") => Some(false),
+        Some("top_folder") => Some(true),
+        Some(other) => {
+            return Err(status::Custom(
+                Status::BadRequest,
+                format!("Unsupported group_by '{}'", other),
+            ));
+        }
+    };
+    let excluded: Vec<String> = exclude
+        .as_deref()
+        .unwrap_or("")
+        .split(',')
+        .map(|term| term.trim().to_string())
+        .filter(|term| !term.is_empty())
+        .collect();
+
+    let crop_marker = crop_marker.unwrap_or_else(search::default_crop_marker);
+    let limit = limit
+        .unwrap_or(search::DEFAULT_SEARCH_LIMIT)
+        .min(search::MAX_SEARCH_LIMIT);
+    let search_query = search::with_excluded_terms(&query, &excluded);
+    match search::search_notes_async(
+        vault.name(),
+        &search_query,
+        &crop_marker,
+        limit,
+        offset.unwrap_or(0),
+        &tag,
+    )
+    .await
+    {
+        Ok(result) => {
+            let mut results = search::without_excluded(result.hits, &excluded);
+            if matches!(with_lines, Some("1" | "true")) {
+                for hit in results
+                    .iter_mut()
+                    .filter(|hit| hit.kind == search::DocumentKind::Note)
+                {
+                    hit.first_match_line = first_match_line(vault.name(), &hit.relpath, &query);
+                }
+            }
+            let total = results.len();
+            if let Err(e) = search_log::record(&query, total) {
+                eprintln!("Failed to log search '{}': {}", query, e);
+            }
+            Ok(Json(match top_level {
+                None => SearchOutput::Flat(SearchResponse {
+                    total,
+                    estimated_total_hits: result.estimated_total_hits,
+                    results,
+                }),
+                Some(top_level) => SearchOutput::Grouped(GroupedSearchResponse {
+                    total,
+                    estimated_total_hits: result.estimated_total_hits,
+                    groups: search::group_by_folder(results, top_level)
+                        .into_iter()
+                        .map(|(folder, results)| SearchGroup { folder, results })
+                        .collect(),
+                }),
+            }))
+        }
+        Err(e) => Err(search_error(e)),
+    }
+}
+
+/// The status for a failed search: 503 while MeiliSearch can't be reached, so clients can
+/// tell an outage from a bad query.
+fn search_error(e: Box<dyn std::error::Error>) -> status::Custom<String> {
+    if search::is_unavailable(e.as_ref()) {
+        status::Custom(
+            Status::ServiceUnavailable,
+            format!("Search is unavailable: {}", e),
+        )
+    } else {
+        status::Custom(Status::InternalServerError, e.to_string())
     }
 }
 
-#[launch]
-async fn rocket() -> _ {
+/// The line of a note file where one of the query's terms first appears. Excluded (`-term`)
+/// terms are ignored and quotes around phrases dropped.
+fn first_match_line(vault_name: &str, relpath: &str, query: &str) -> Option<usize> {
+    let raw = notemancy_core::crud::read_note(vault_name, relpath, true).ok()?;
+    let terms: Vec<&str> = query
+        .split_whitespace()
+        .filter(|term| !term.starts_with('-'))
+        .map(|term| term.trim_matches('"'))
+        .filter(|term| !term.is_empty())
+        .collect();
+    utils::find_matches(&raw, &terms.join(" "))
+        .first()
+        .map(|found| found.line)
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SearchCount {
+    /// Number of notes matching the query
+    pub total: usize,
+}
+
+/// Number of notes matching a query, for showing a result count without the results.
+#[get("/notes/search/count?<query>")]
+async fn search_count(
+    vault: Vault,
+    query: String,
+) -> Result<Json<SearchCount>, status::Custom<String>> {
+    search::count_matches_async(vault.name(), &query)
+        .await
+        .map(|total| Json(SearchCount { total }))
+        .map_err(search_error)
+}
+
+#[rocket::main]
+async fn main() -> Result<(), Box<rocket::Error>> {
+    build_rocket().await.launch().await.map_err(Box::new)?;
+    println!("Shutdown complete");
+    Ok(())
+}
+
+/// Builds the default vault's search index in the background once the server is up, so
+/// startup doesn't wait on MeiliSearch. If it can't be reached the failure is logged and the
+/// server keeps running; searches return 503 until it is back and `/notes/reindex` is called.
+async fn build_search_index() {
     println!("Building search index...");
-    match search::build_search_index_async("main").await {
+    match search::build_search_index_async(&vault::default_vault_name())
+        .await
+        .map_err(|e| e.to_string())
+    {
         Ok(_) => println!("Search index built successfully"),
         Err(e) => eprintln!("Failed to build search index: {}", e),
     }
-    let allowed_origins = AllowedOrigins::some_exact(&["http://localhost:5173"]);
+}
+
+async fn build_rocket() -> Rocket<Build> {
+    let address = shutdown::figment()
+        .extract_inner("address")
+        .unwrap_or(rocket::Config::default().address);
+    if let Err(e) = access::check_write_protection(address) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+    if let Err(e) = search::init_meilisearch() {
+        eprintln!(
+            "Failed to create MeiliSearch client for {}: {}",
+            search::meilisearch_url(),
+            e
+        );
+        std::process::exit(1);
+    }
+    if watch::enabled() {
+        rocket::tokio::spawn(watch::run(vault::default_vault_name()));
+    }
+    let allowed_origins = match cors::allowed_origins() {
+        Ok(origins) => origins,
+        Err(e) => {
+            eprintln!("{} in NOTEMANCY_CORS_ORIGINS", e);
+            std::process::exit(1);
+        }
+    };
 
     let cors = CorsOptions {
         allowed_origins,
-        allowed_headers: AllowedHeaders::some(&["Authorization", "Accept", "Content-Type"]),
-        allow_credentials: true,
+        allowed_headers: AllowedHeaders::some(&[
+            "Authorization",
+            "Accept",
+            "Content-Type",
+            vault::VAULT_HEADER,
+        ]),
+        allowed_methods: cors::allowed_methods(),
+        allow_credentials: cors::allow_credentials(),
         ..Default::default()
     }
     .to_cors()
     .expect("error creating CORS fairing");
 
-    rocket::build().attach(cors).mount(
-        "/",
-        routes![
-            hello,
-            notes_tree,
-            note_content,
-            upload_note,
-            update_note,
-            search_notes
-        ],
-    )
+    let mut rocket = rocket::custom(shutdown::figment())
+        .attach(vault::VaultPrefix)
+        .attach(cors)
+        .attach(shutdown::LogShutdown)
+        .attach(cache::InvalidateOnWrite)
+        .attach(shape::ShapeJson)
+        .attach(AdHoc::on_liftoff("Build search index", |_| {
+            Box::pin(async {
+                rocket::tokio::spawn(build_search_index());
+            })
+        }))
+        .register(
+            "/",
+            catchers![
+                access::forbidden,
+                access::unauthorized,
+                errors::bad_request,
+                errors::unprocessable,
+                vault::not_found
+            ],
+        )
+        .mount(
+            "/",
+            routes![
+                hello,
+                server_config,
+                notes_tree,
+                notes_folders,
+                create_folder,
+                note_content,
+                note_raw,
+                note_by_title,
+                render::render,
+                note_unfurl,
+                notes_by_length,
+                disk_usage,
+                duplicate_notes,
+                frontmatter_issues,
+                export_csv,
+                concat_notes,
+                upload_note,
+                attachments::upload_multipart,
+                attachments::note_first_image,
+                update_note,
+                upsert_note,
+                delete_note,
+                batch_delete,
+                import::import_zip,
+                parse_note,
+                title_preview,
+                default_frontmatter,
+                search_in_note,
+                links::local_graph,
+                links::note_backlinks,
+                linkcheck::check_external_links,
+                tags::stats_by_tag,
+                tags::rename_tag_route,
+                tags::suggest,
+                tags::bulk_tag,
+                outline::outline_all,
+                archive_note,
+                unarchive_note,
+                move_note_route,
+                rename_note,
+                undo_last_move,
+                copy_note,
+                versions::note_history,
+                versions::restore_version,
+                git::restore_from_git,
+                set_frontmatter_key,
+                convert_frontmatter,
+                reindex_notes,
+                repair_index,
+                index_stats,
+                watch::watch_status,
+                watch::note_events,
+                search_notes,
+                search_count,
+                search_log::top_searches
+            ],
+        );
+
+    // Metrics are opt-in so operational data isn't exposed publicly by default.
+    if metrics::enabled() {
+        metrics::init();
+        rocket = rocket
+            .attach(metrics::MetricsFairing)
+            .mount("/", routes![metrics::metrics]);
+    }
+
+    rocket
 }
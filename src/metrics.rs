@@ -0,0 +1,191 @@
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Status};
+use rocket::response::status;
+use rocket::{Data, Request, Response};
+use std::time::Instant;
+
+use crate::search;
+use crate::utils;
+use crate::vault;
+
+// Registry holding every metric exposed on /metrics
+static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
+
+/// Requests served, labelled by method, route and response status
+pub static HTTP_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new("notemancy_http_requests_total", "HTTP requests served"),
+            &["method", "route", "status"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Request duration in seconds, labelled by method and route
+pub static HTTP_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "notemancy_http_request_duration_seconds",
+                "HTTP request duration in seconds",
+            ),
+            &["method", "route"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Responses with a 4xx or 5xx status, labelled by route
+pub static HTTP_ERRORS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "notemancy_http_errors_total",
+                "HTTP responses with an error status",
+            ),
+            &["route", "status"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Search queries issued against MeiliSearch
+pub static SEARCH_QUERIES: Lazy<IntCounter> = Lazy::new(|| {
+    register(IntCounter::new("notemancy_search_queries_total", "Search queries executed").unwrap())
+});
+
+/// Index operations, labelled by operation and outcome
+pub static INDEX_OPERATIONS: Lazy<IntCounterVec> = Lazy::new(|| {
+    register(
+        IntCounterVec::new(
+            Opts::new(
+                "notemancy_index_operations_total",
+                "Search index operations",
+            ),
+            &["operation", "outcome"],
+        )
+        .unwrap(),
+    )
+});
+
+/// Number of notes in the vault, refreshed on every scrape
+pub static NOTE_COUNT: Lazy<IntGauge> = Lazy::new(|| {
+    register(IntGauge::new("notemancy_notes", "Number of notes in the vault").unwrap())
+});
+
+/// Number of documents in the search index, refreshed on every scrape
+pub static INDEX_DOCUMENTS: Lazy<IntGauge> = Lazy::new(|| {
+    register(
+        IntGauge::new(
+            "notemancy_index_documents",
+            "Number of documents in the search index",
+        )
+        .unwrap(),
+    )
+});
+
+fn register<T: prometheus::core::Collector + Clone + 'static>(collector: T) -> T {
+    REGISTRY
+        .register(Box::new(collector.clone()))
+        .expect("metric registered twice");
+    collector
+}
+
+/// Whether metrics collection and the /metrics route are enabled.
+///
+/// Metrics are off unless `NOTEMANCY_METRICS` is set to `1` or `true`, so a default
+/// deployment doesn't publicly expose operational data.
+pub fn enabled() -> bool {
+    std::env::var("NOTEMANCY_METRICS")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Register every metric up front so they're exported before their first update
+pub fn init() {
+    Lazy::force(&HTTP_REQUESTS);
+    Lazy::force(&HTTP_DURATION);
+    Lazy::force(&HTTP_ERRORS);
+    Lazy::force(&SEARCH_QUERIES);
+    Lazy::force(&INDEX_OPERATIONS);
+    Lazy::force(&NOTE_COUNT);
+    Lazy::force(&INDEX_DOCUMENTS);
+}
+
+/// Record the outcome of an index operation
+pub fn record_index_operation<T, E>(operation: &str, result: &Result<T, E>) {
+    let outcome = if result.is_ok() { "success" } else { "failure" };
+    INDEX_OPERATIONS
+        .with_label_values(&[operation, outcome])
+        .inc();
+}
+
+// Start time of a request, stored in the request-local cache
+struct RequestStart(Option<Instant>);
+
+/// Fairing that records request counts, durations and errors per route
+pub struct MetricsFairing;
+
+#[rocket::async_trait]
+impl Fairing for MetricsFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "Prometheus metrics",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _: &mut Data<'_>) {
+        req.local_cache(|| RequestStart(Some(Instant::now())));
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        // Use the route template rather than the concrete URI to keep label cardinality low.
+        let route = req
+            .route()
+            .map(|r| r.uri.path().to_string())
+            .unwrap_or_else(|| "unmatched".to_string());
+        let method = req.method().as_str();
+        let status = res.status().code.to_string();
+
+        HTTP_REQUESTS
+            .with_label_values(&[method, &route, &status])
+            .inc();
+        if res.status().code >= 400 {
+            HTTP_ERRORS.with_label_values(&[&route, &status]).inc();
+        }
+        if let Some(start) = req.local_cache(|| RequestStart(None)).0 {
+            HTTP_DURATION
+                .with_label_values(&[method, &route])
+                .observe(start.elapsed().as_secs_f64());
+        }
+    }
+}
+
+/// Render all metrics in the Prometheus text exposition format
+#[get("/metrics")]
+pub async fn metrics() -> Result<(ContentType, String), status::Custom<String>> {
+    if let Ok(notes) = utils::list_note_relpaths(&vault::default_vault_name()).map(|n| n.len()) {
+        NOTE_COUNT.set(notes as i64);
+    }
+    if let Ok(documents) = search::document_count_async(&vault::default_vault_name()).await {
+        INDEX_DOCUMENTS.set(documents as i64);
+    }
+
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&REGISTRY.gather(), &mut buffer)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    let body = String::from_utf8(buffer)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    Ok((
+        ContentType::new("text", "plain").with_params(("version", "0.0.4")),
+        body,
+    ))
+}
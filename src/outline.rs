@@ -0,0 +1,133 @@
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::{Serialize, json::Json};
+use std::collections::HashMap;
+
+use crate::frontmatter;
+use crate::utils::{self, TreeNode, TreeOptions};
+use crate::vault::Vault;
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(crate = "rocket::serde")]
+pub struct Heading {
+    /// 1 for `#`, up to 6 for `######`
+    pub level: usize,
+    pub text: String,
+    /// Anchor for the heading, unique within the note
+    pub slug: String,
+}
+
+/// Turns heading text into a GitHub-style anchor: lowercase, punctuation dropped and
+/// spaces replaced by hyphens.
+pub fn slugify(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Extracts the ATX headings (`# Title` to `###### Title`) of a note body, skipping fenced
+/// code blocks. Repeated slugs get `-1`, `-2`, ... appended.
+pub fn extract_headings(body: &str) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut in_code_block = false;
+
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        let level = trimmed.chars().take_while(|&c| c == '#').count();
+        let Some(rest) = trimmed.get(level..) else {
+            continue;
+        };
+        if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+            continue;
+        }
+        let text = rest.trim().trim_end_matches('#').trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        let base = slugify(text);
+        let count = seen.entry(base.clone()).or_insert(0);
+        let slug = if *count == 0 {
+            base
+        } else {
+            format!("{}-{}", base, count)
+        };
+        *count += 1;
+        headings.push(Heading {
+            level,
+            text: text.to_string(),
+            slug,
+        });
+    }
+    headings
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct NoteOutline {
+    pub relpath: String,
+    pub title: String,
+    pub headings: Vec<Heading>,
+}
+
+/// Collects the notes of a tree in display order as `(relpath, title)` pairs.
+pub fn tree_notes<'a>(nodes: &'a [TreeNode], notes: &mut Vec<(&'a str, &'a str)>) {
+    for node in nodes {
+        if let Some(children) = &node.children {
+            tree_notes(children, notes);
+        } else if let Some(relpath) = &node.relpath {
+            notes.push((relpath, node.title.as_deref().unwrap_or("")));
+        }
+    }
+}
+
+/// The headings of every note under `folder` (the whole vault when omitted), in the
+/// same order as the file tree.
+#[get("/notes/outline-all?<folder>")]
+pub fn outline_all(
+    vault: Vault,
+    folder: Option<String>,
+) -> Result<Json<Vec<NoteOutline>>, status::Custom<String>> {
+    let tree = utils::build_file_tree(vault.name(), &TreeOptions::default())
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    let mut notes = Vec::new();
+    tree_notes(&tree, &mut notes);
+
+    let prefix = folder
+        .as_deref()
+        .map(|folder| folder.trim_matches('/'))
+        .filter(|folder| !folder.is_empty())
+        .map(|folder| format!("{}/", folder));
+    let mut outlines = Vec::new();
+    for (relpath, title) in notes {
+        if prefix
+            .as_ref()
+            .is_some_and(|prefix| !relpath.starts_with(prefix))
+        {
+            continue;
+        }
+        let raw = notemancy_core::crud::read_note(vault.name(), relpath, true)
+            .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+        let (_, body) = frontmatter::parse_frontmatter(&raw);
+        outlines.push(NoteOutline {
+            relpath: relpath.to_string(),
+            title: title.to_string(),
+            headings: extract_headings(&body),
+        });
+    }
+    Ok(Json(outlines))
+}
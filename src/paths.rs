@@ -0,0 +1,198 @@
+use rocket::http::Status;
+use rocket::response::status;
+use std::error::Error;
+use std::path::{Component, Path, PathBuf};
+
+use crate::utils;
+
+/// The root directory of a vault.
+pub fn vault_root(vault_name: &str) -> Result<PathBuf, Box<dyn Error>> {
+    Ok(PathBuf::from(notemancy_core::config::get_vault_dir(
+        vault_name,
+    )?))
+}
+
+/// Checks that a relpath from a client names something inside the vault: it must be
+/// relative and can't contain `..` or `.` components. Returns a message suitable for a 400.
+pub fn validate_relpath(relpath: &str) -> Result<(), String> {
+    let path = Path::new(relpath);
+    if relpath.is_empty()
+        || path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        Err(format!("'{}' is not a path inside the vault", relpath))
+    } else {
+        Ok(())
+    }
+}
+
+/// Checks that `path` (below `root`) doesn't leave the vault through a symlink. Paths that
+/// don't exist yet are checked through their closest existing parent folder.
+pub fn check_within(root: &Path, path: &Path) -> Result<(), String> {
+    let escapes = || {
+        format!(
+            "'{}' is not a path inside the vault",
+            relpath_of(root, path)
+        )
+    };
+    let root = root.canonicalize().map_err(|e| e.to_string())?;
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.symlink_metadata().is_ok())
+        .ok_or_else(escapes)?;
+    match existing.canonicalize() {
+        Ok(resolved) if resolved.starts_with(&root) => Ok(()),
+        // A dangling symlink can't be followed, so it can't be trusted either.
+        _ => Err(escapes()),
+    }
+}
+
+/// Resolves a relpath from a client to its path in the vault.
+///
+/// Fails with 400 when the relpath would point outside the vault: when it is absolute,
+/// has `..` components, or passes through a symlink to somewhere outside.
+pub fn resolve_in_vault(
+    vault_name: &str,
+    relpath: &str,
+) -> Result<PathBuf, status::Custom<String>> {
+    let root = vault_root(vault_name)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    resolve_under(&root, relpath).map_err(|e| status::Custom(Status::BadRequest, e))
+}
+
+/// Resolves a relpath to its path below `root`, with the checks of `resolve_in_vault`.
+pub fn resolve_under(root: &Path, relpath: &str) -> Result<PathBuf, String> {
+    validate_relpath(relpath)?;
+    let path = root.join(relpath);
+    check_within(root, &path)?;
+    Ok(path)
+}
+
+/// The path of `path` relative to `root`, always with `/` as the separator whatever the
+/// platform. Paths outside `root` are returned whole.
+pub fn relpath_of(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| match c {
+            // Joining with `/` puts it back, without doubling it.
+            Component::RootDir => "".into(),
+            c => c.as_os_str().to_string_lossy(),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Whether the path has one of the recognised note extensions (compared case-insensitively).
+pub fn is_note_file(path: &Path) -> bool {
+    has_note_extension(path, &utils::note_extensions())
+}
+
+/// Whether the path has one of `extensions` (lowercased, without dots), ignoring case.
+fn has_note_extension(path: &Path, extensions: &[String]) -> bool {
+    let extension = path
+        .extension()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    extensions.contains(&extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn relpaths_must_stay_inside_the_vault() {
+        assert!(validate_relpath("note.md").is_ok());
+        assert!(validate_relpath("projects/sub/note.md").is_ok());
+        for relpath in [
+            "",
+            "../note.md",
+            "projects/../../note.md",
+            "./note.md",
+            "/etc/passwd",
+        ] {
+            assert!(validate_relpath(relpath).is_err(), "accepted '{}'", relpath);
+        }
+    }
+
+    #[test]
+    fn relpaths_use_forward_slashes() {
+        let root = Path::new("/vault");
+        assert_eq!(
+            relpath_of(root, &root.join("projects").join("sub").join("note.md")),
+            "projects/sub/note.md"
+        );
+        assert_eq!(
+            relpath_of(root, Path::new("/elsewhere/note.md")),
+            "/elsewhere/note.md"
+        );
+    }
+
+    #[test]
+    fn paths_below_the_root_are_within_it() {
+        let vault = tempfile::tempdir().unwrap();
+        let root = vault.path();
+        fs::create_dir(root.join("projects")).unwrap();
+        fs::write(root.join("projects/note.md"), "").unwrap();
+
+        assert!(check_within(root, &root.join("projects/note.md")).is_ok());
+        // Not created yet, checked through the closest existing folder
+        assert!(check_within(root, &root.join("projects/new/note.md")).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_out_of_the_root_are_not_within_it() {
+        let vault = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let root = vault.path();
+        fs::write(outside.path().join("secret.md"), "").unwrap();
+        std::os::unix::fs::symlink(outside.path(), root.join("linked")).unwrap();
+        std::os::unix::fs::symlink(root.join("missing"), root.join("dangling.md")).unwrap();
+
+        assert!(check_within(root, &root.join("linked/secret.md")).is_err());
+        assert!(check_within(root, &root.join("linked/new.md")).is_err());
+        assert!(check_within(root, &root.join("dangling.md")).is_err());
+    }
+
+    #[test]
+    fn relpaths_resolve_below_the_root() {
+        let vault = tempfile::tempdir().unwrap();
+        let root = vault.path();
+        fs::create_dir_all(root.join("projects/sub")).unwrap();
+
+        assert_eq!(
+            resolve_under(root, "projects/sub/note.md").unwrap(),
+            root.join("projects/sub/note.md")
+        );
+        assert!(resolve_under(root, "../note.md").is_err());
+        assert!(resolve_under(root, "projects/../../note.md").is_err());
+        assert!(resolve_under(root, "/etc/passwd").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn relpaths_through_symlinks_out_of_the_root_do_not_resolve() {
+        let vault = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let root = vault.path();
+        std::os::unix::fs::symlink(outside.path(), root.join("linked")).unwrap();
+
+        assert!(resolve_under(root, "linked/note.md").is_err());
+    }
+
+    #[test]
+    fn configured_extensions_are_notes() {
+        let extensions = ["md", "markdown", "mdx"].map(String::from);
+        let is_note = |path: &str| has_note_extension(Path::new(path), &extensions);
+        assert!(is_note("projects/plan.mdx"));
+        assert!(is_note("projects/Plan.MDX"));
+        assert!(is_note("projects/plan.md"));
+        assert!(!is_note("projects/plan.txt"));
+        assert!(!is_note("projects/mdx"));
+    }
+}
@@ -0,0 +1,113 @@
+use pulldown_cmark::{Options, Parser, html};
+use rocket::serde::{Deserialize, Serialize, json::Json};
+
+use crate::errors::JsonBody;
+use crate::frontmatter;
+use crate::outline;
+
+/// Percent-encodes text for use as a query parameter value.
+fn encode_query_value(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Rewrites wikilinks as markdown links to `/notes/by-title`, so they render as regular
+/// links. `[[Title|alias]]` keeps the alias as link text and `[[Title#Heading]]` links to
+/// the heading's anchor. Embeds (`![[...]]`) and fenced code blocks are left untouched.
+pub fn wikilinks_to_markdown(body: &str) -> String {
+    let mut converted = String::with_capacity(body.len());
+    let mut in_code_block = false;
+
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block {
+            converted.push_str(line);
+            continue;
+        }
+
+        let mut rest = line;
+        while let Some(start) = rest.find("[[") {
+            let Some(end) = rest[start + 2..].find("]]") else {
+                break;
+            };
+            let inner = &rest[start + 2..start + 2 + end];
+            let is_embed = rest[..start].ends_with('!');
+            converted.push_str(&rest[..start]);
+            if is_embed || inner.trim().is_empty() {
+                converted.push_str(&rest[start..start + end + 4]);
+            } else {
+                let (target, alias) = match inner.split_once('|') {
+                    Some((target, alias)) => (target, Some(alias.trim())),
+                    None => (inner, None),
+                };
+                let (title, heading) = match target.split_once('#') {
+                    Some((title, heading)) => (title.trim(), Some(heading.trim())),
+                    None => (target.trim(), None),
+                };
+                let anchor = heading
+                    .map(|heading| format!("#{}", outline::slugify(heading)))
+                    .unwrap_or_default();
+                converted.push_str(&format!(
+                    "[{}](/notes/by-title?title={}{})",
+                    alias.unwrap_or(target.trim()),
+                    encode_query_value(title),
+                    anchor
+                ));
+            }
+            rest = &rest[start + end + 4..];
+        }
+        converted.push_str(rest);
+    }
+    converted
+}
+
+/// Renders a note body to sanitized HTML.
+///
+/// Tables, footnotes, strikethrough and task lists are enabled. Raw HTML in the note is
+/// kept only where it is safe (scripts, event handlers and the like are stripped).
+pub fn render_markdown(body: &str) -> String {
+    let markdown = wikilinks_to_markdown(body);
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, Parser::new_ext(&markdown, options));
+    ammonia::clean(&unsafe_html)
+}
+
+/// Renders the complete contents of a note, frontmatter excluded, to sanitized HTML.
+pub fn render_note(raw: &str) -> String {
+    let (_, body) = frontmatter::parse_frontmatter(raw);
+    render_markdown(&body)
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RenderRequest {
+    /// The complete contents of the note, frontmatter included
+    pub content: String,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RenderedNote {
+    pub html: String,
+}
+
+/// Render unsaved note contents to HTML, for live previews.
+#[post("/notes/render", data = "<req>")]
+pub fn render(req: JsonBody<RenderRequest>) -> Json<RenderedNote> {
+    Json(RenderedNote {
+        html: render_note(&req.content),
+    })
+}
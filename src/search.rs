@@ -1,33 +1,137 @@
+use chrono::{DateTime, Utc};
 use meilisearch_sdk::client::Client;
-use meilisearch_sdk::settings::Settings;
+use meilisearch_sdk::documents::DocumentsQuery;
+use meilisearch_sdk::indexes::Index;
+use meilisearch_sdk::search::Selectors;
+use meilisearch_sdk::settings::{LocalizedAttributes, Settings};
 use once_cell::sync::Lazy;
+use rocket::futures::stream::{self, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::error::Error;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 use std::time::Duration;
 
-// Create a static client instance
-static CLIENT: Lazy<Client> = Lazy::new(|| {
-    // The URL and API key should ideally come from configuration
-    Client::new("http://localhost:7700", Some("aSampleMasterKey")).unwrap()
-});
+use crate::frontmatter;
+use crate::metrics;
+use crate::title;
 
-// Counter for document IDs
-static COUNTER: AtomicUsize = AtomicUsize::new(1);
+// Created by `init_meilisearch` at startup, before any route can use it
+static CLIENT: Lazy<Client> =
+    Lazy::new(|| new_client().expect("the MeiliSearch client is checked by init_meilisearch"));
 
-const INDEX_NAME: &str = "notes";
+/// The MeiliSearch server, from `MEILISEARCH_URL` (default `http://localhost:7700`).
+pub fn meilisearch_url() -> String {
+    std::env::var("MEILISEARCH_URL")
+        .ok()
+        .map(|url| url.trim().trim_end_matches('/').to_string())
+        .filter(|url| !url.is_empty())
+        .unwrap_or_else(|| "http://localhost:7700".to_string())
+}
+
+/// The key sent to MeiliSearch, from `MEILISEARCH_API_KEY` (default `aSampleMasterKey`).
+fn meilisearch_api_key() -> String {
+    std::env::var("MEILISEARCH_API_KEY").unwrap_or_else(|_| "aSampleMasterKey".to_string())
+}
+
+fn new_client() -> Result<Client, String> {
+    Client::new(meilisearch_url(), Some(meilisearch_api_key())).map_err(|e| e.to_string())
+}
+
+/// Creates the MeiliSearch client. Fails only on bad configuration (such as an API key that
+/// can't be sent as a header); the server being unreachable is not checked here. Searches
+/// made while it is unreachable fail with `is_unavailable` errors instead.
+pub fn init_meilisearch() -> Result<(), String> {
+    new_client()?;
+    Lazy::force(&CLIENT);
+    Ok(())
+}
+
+/// Whether an error means the MeiliSearch server couldn't be reached, rather than that it
+/// rejected the request.
+pub fn is_unavailable(e: &(dyn Error + 'static)) -> bool {
+    matches!(
+        e.downcast_ref::<meilisearch_sdk::errors::Error>(),
+        Some(meilisearch_sdk::errors::Error::HttpError(_))
+    )
+}
+
+/// The MeiliSearch index holding a vault's documents. Each vault has its own index, so
+/// rebuilding, repairing or searching one vault never touches another's documents. Vault
+/// names that can't be used in an index uid are hashed.
+pub fn index_uid(vault_name: &str) -> String {
+    if is_valid_document_id(vault_name) && vault_name.len() <= 100 {
+        format!("notes-{}", vault_name)
+    } else {
+        format!("notes-{}", content_hash(vault_name))
+    }
+}
+
+// Indexes whose settings have been applied since the server started
+static CONFIGURED: Lazy<Mutex<HashSet<String>>> = Lazy::new(Default::default);
+
+/// The index of a vault, created and configured the first time it is used.
+async fn vault_index(vault_name: &str) -> Result<Index, Box<dyn Error>> {
+    let uid = index_uid(vault_name);
+    let configured = CONFIGURED.lock().unwrap().contains(&uid);
+    if !configured {
+        configure_meilisearch_async(vault_name).await?;
+    }
+    Ok(CLIENT.index(uid))
+}
+
+/// What a search document stands for
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentKind {
+    #[default]
+    Note,
+    /// A non-note file, indexed by path only (see `index_attachments`)
+    Attachment,
+}
 
 /// A document representing a note for indexing in MeiliSearch
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct NoteDocument {
-    /// The unique ID for the document in MeiliSearch
-    pub id: usize,
+    /// The unique ID for the document in MeiliSearch (see `IdScheme`)
+    pub id: String,
     /// The relative path to the note file
     pub relpath: String,
+    /// Whether this is a note or an attachment
+    #[serde(default)]
+    pub kind: DocumentKind,
     /// The title of the note
     pub title: String,
     /// The content of the note (without frontmatter)
     pub content: String,
+    /// The tags of the note's frontmatter, filterable in searches
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Searchable text from selected frontmatter fields (see `indexed_frontmatter_fields`)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub metadata: String,
+    /// Hash of the note file's raw contents, used to detect notes changed since indexing
+    #[serde(default)]
+    pub hash: String,
+    /// The part of the content around the match; only set on search hits, never indexed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// A snippet per field the query matched; only set on search hits, never indexed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippets: Option<Snippets>,
+    /// The 1-based line of the note file holding the first query term; only set on search
+    /// hits when requested, never indexed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_match_line: Option<usize>,
+}
+
+/// The parts of a search hit's fields around the match, for the fields that matched
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Snippets {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
 }
 
 /// A struct for search results
@@ -37,33 +141,123 @@ pub struct SearchResult {
     pub hits_count: usize,
     /// The actual note documents found
     pub hits: Vec<NoteDocument>,
+    /// MeiliSearch's estimate of all matches, beyond the requested page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_total_hits: Option<usize>,
 }
 
 // Asynchronous MeiliSearch functions that will be called by the synchronous wrappers
 
 /// Configuration for MeiliSearch - async version
-pub async fn configure_meilisearch_async() -> Result<(), Box<dyn Error>> {
+pub async fn configure_meilisearch_async(vault_name: &str) -> Result<(), Box<dyn Error>> {
+    let result = configure_index(&index_uid(vault_name)).await;
+    metrics::record_index_operation("configure", &result);
+    result
+}
+
+/// Load the synonyms map from the file named by `MEILISEARCH_SYNONYMS_FILE`.
+///
+/// The file is JSON or YAML mapping a word to its synonyms, e.g. `{"k8s": ["kubernetes"]}`.
+/// Returns an empty map when the variable is unset, which clears any previous synonyms.
+fn load_synonyms() -> Result<HashMap<String, Vec<String>>, Box<dyn Error>> {
+    let path = match std::env::var("MEILISEARCH_SYNONYMS_FILE") {
+        Ok(path) if !path.trim().is_empty() => path,
+        _ => return Ok(HashMap::new()),
+    };
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read synonyms file '{}': {}", path, e))?;
+    // YAML is a superset of JSON, so one parser handles both formats.
+    let synonyms = serde_yaml::from_str(&content)
+        .map_err(|e| format!("Failed to parse synonyms file '{}': {}", path, e))?;
+    Ok(synonyms)
+}
+
+/// Load the stop words from the file named by `MEILISEARCH_STOP_WORDS_FILE`.
+///
+/// The file is a JSON or YAML list of words (e.g. `["the", "a", "der", "die", "das"]`).
+/// Stop words are ignored in queries, so common words don't dominate ranking. Returns an
+/// empty list when the variable is unset, which clears any previous stop words.
+fn load_stop_words() -> Result<Vec<String>, Box<dyn Error>> {
+    let path = match std::env::var("MEILISEARCH_STOP_WORDS_FILE") {
+        Ok(path) if !path.trim().is_empty() => path,
+        _ => return Ok(Vec::new()),
+    };
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read stop words file '{}': {}", path, e))?;
+    let stop_words = serde_yaml::from_str(&content)
+        .map_err(|e| format!("Failed to parse stop words file '{}': {}", path, e))?;
+    Ok(stop_words)
+}
+
+/// Returns the languages of the vault, from the comma-separated ISO 639 codes in
+/// `NOTEMANCY_SEARCH_LOCALES` (e.g. `eng,deu`).
+///
+/// They are applied to every attribute so MeiliSearch tokenizes and segments text for those
+/// languages. Unset, MeiliSearch detects the language of each document itself.
+pub fn search_locales() -> Vec<String> {
+    std::env::var("NOTEMANCY_SEARCH_LOCALES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|locale| locale.trim().to_lowercase())
+        .filter(|locale| !locale.is_empty())
+        .collect()
+}
+
+/// Fields that can be made searchable
+const SEARCHABLE_FIELDS: [&str; 4] = ["title", "content", "metadata", "relpath"];
+
+/// Returns the searchable attributes, most important first.
+///
+/// MeiliSearch's `attribute` ranking rule ranks a match in an earlier attribute above one in
+/// a later attribute, so the order works as field boosting. The default is `title`,
+/// `content`, `metadata`, `relpath`: title matches outrank body matches, and the body stays
+/// ahead of frontmatter metadata. Override it with a comma-separated list in
+/// `NOTEMANCY_SEARCHABLE_ATTRIBUTES`; unknown fields are ignored and omitted fields aren't
+/// searched.
+pub fn searchable_attributes() -> Vec<String> {
+    parse_searchable_attributes(
+        &std::env::var("NOTEMANCY_SEARCHABLE_ATTRIBUTES").unwrap_or_default(),
+    )
+}
+
+fn parse_searchable_attributes(configured: &str) -> Vec<String> {
+    let mut attributes: Vec<String> = Vec::new();
+    for field in configured.split(',').map(str::trim) {
+        if SEARCHABLE_FIELDS.contains(&field) && !attributes.iter().any(|f| f == field) {
+            attributes.push(field.to_string());
+        }
+    }
+    if attributes.is_empty() {
+        SEARCHABLE_FIELDS
+            .iter()
+            .map(|field| field.to_string())
+            .collect()
+    } else {
+        attributes
+    }
+}
+
+/// MeiliSearch ranking rules. `attribute` is what makes the order of
+/// `searchable_attributes` matter.
+const RANKING_RULES: [&str; 6] = [
+    "words",
+    "typo",
+    "proximity",
+    "attribute",
+    "sort",
+    "exactness",
+];
+
+/// Create the index if needed and apply its settings
+async fn configure_index(uid: &str) -> Result<(), Box<dyn Error>> {
     // Create the index if it doesn't exist
-    if let Err(_) = CLIENT.get_index(INDEX_NAME).await {
-        CLIENT.create_index(INDEX_NAME, Some("id")).await?;
-    }
-
-    // Configure the index settings
-    let settings = Settings::new()
-        .with_searchable_attributes(&["title", "content", "relpath"])
-        .with_displayed_attributes(&["id", "relpath", "title", "content"])
-        .with_filterable_attributes(&["relpath"])
-        .with_ranking_rules(&[
-            "words",
-            "typo",
-            "proximity",
-            "attribute",
-            "sort",
-            "exactness",
-        ]);
+    if let Err(_) = CLIENT.get_index(uid).await {
+        CLIENT.create_index(uid, Some("id")).await?;
+    }
 
+    let settings = index_settings()?;
     let task = CLIENT
-        .index(INDEX_NAME)
+        .index(uid)
         .set_settings(&settings)
         .await?
         .wait_for_completion(&CLIENT, None, Some(Duration::from_secs(60)))
@@ -73,33 +267,60 @@ pub async fn configure_meilisearch_async() -> Result<(), Box<dyn Error>> {
         return Err(format!("Failed to configure index: {:?}", task.unwrap_failure()).into());
     }
 
+    CONFIGURED.lock().unwrap().insert(uid.to_string());
     Ok(())
 }
 
-/// Add or update a note in the search index - async version
-pub async fn index_note_async(note: &NoteDocument) -> Result<(), Box<dyn Error>> {
-    let task = CLIENT
-        .index(INDEX_NAME)
-        .add_documents(&[note.clone()], Some("id"))
-        .await?
-        .wait_for_completion(&CLIENT, None, Some(Duration::from_secs(60)))
-        .await?;
+/// The settings `configure_index` applies to every index
+fn index_settings() -> Result<Settings, Box<dyn Error>> {
+    // Synonyms and stop words are re-read on every (re)index so edits to the files take effect.
+    let synonyms = load_synonyms()?;
+    let stop_words = load_stop_words()?;
+    let locales = search_locales();
+    let localized_attributes = if locales.is_empty() {
+        Vec::new()
+    } else {
+        vec![LocalizedAttributes {
+            locales,
+            attribute_patterns: vec!["*".to_string()],
+        }]
+    };
 
-    if task.is_failure() {
-        return Err(format!("Failed to index note: {:?}", task.unwrap_failure()).into());
-    }
+    Ok(Settings::new()
+        .with_synonyms(synonyms)
+        .with_stop_words(stop_words)
+        .with_localized_attributes(localized_attributes)
+        .with_searchable_attributes(searchable_attributes())
+        .with_displayed_attributes(&["id", "relpath", "kind", "title", "content", "tags", "hash"])
+        .with_filterable_attributes(&["relpath", "kind", "tags"])
+        .with_ranking_rules(&RANKING_RULES))
+}
 
-    Ok(())
+/// Add or update a note in the search index - async version
+pub async fn index_note_async(vault_name: &str, note: &NoteDocument) -> Result<(), Box<dyn Error>> {
+    let result = add_documents(vault_name, std::slice::from_ref(note)).await;
+    metrics::record_index_operation("index_note", &result);
+    result
 }
 
 /// Add or update multiple notes in the search index - async version
-pub async fn index_notes_async(notes: &[NoteDocument]) -> Result<(), Box<dyn Error>> {
+pub async fn index_notes_async(
+    vault_name: &str,
+    notes: &[NoteDocument],
+) -> Result<(), Box<dyn Error>> {
     if notes.is_empty() {
         return Ok(());
     }
 
-    let task = CLIENT
-        .index(INDEX_NAME)
+    let result = add_documents(vault_name, notes).await;
+    metrics::record_index_operation("index_notes", &result);
+    result
+}
+
+/// Add documents to the index and wait for the task to finish
+async fn add_documents(vault_name: &str, notes: &[NoteDocument]) -> Result<(), Box<dyn Error>> {
+    let index = vault_index(vault_name).await?;
+    let task = index
         .add_documents(notes, Some("id"))
         .await?
         .wait_for_completion(&CLIENT, None, Some(Duration::from_secs(60)))
@@ -113,9 +334,12 @@ pub async fn index_notes_async(notes: &[NoteDocument]) -> Result<(), Box<dyn Err
 }
 
 /// Search notes by exact relpath - async version
-pub async fn search_by_relpath_async(relpath: &str) -> Result<SearchResult, Box<dyn Error>> {
-    let results = CLIENT
-        .index(INDEX_NAME)
+pub async fn search_by_relpath_async(
+    vault_name: &str,
+    relpath: &str,
+) -> Result<SearchResult, Box<dyn Error>> {
+    let index = vault_index(vault_name).await?;
+    let results = index
         .search()
         .with_filter(&format!("relpath = '{}'", relpath.replace("'", "\\'")))
         .execute::<NoteDocument>()
@@ -124,13 +348,80 @@ pub async fn search_by_relpath_async(relpath: &str) -> Result<SearchResult, Box<
     Ok(SearchResult {
         hits_count: results.hits.len(),
         hits: results.hits.into_iter().map(|hit| hit.result).collect(),
+        estimated_total_hits: results.estimated_total_hits,
     })
 }
 
 /// Delete a note from the search index by its relpath - async version
-pub async fn delete_note_from_index_async(relpath: &str) -> Result<(), Box<dyn Error>> {
+pub async fn delete_note_from_index_async(
+    vault_name: &str,
+    relpath: &str,
+) -> Result<(), Box<dyn Error>> {
+    let result = delete_documents_for(vault_name, relpath).await;
+    metrics::record_index_operation("delete_note", &result);
+    result
+}
+
+/// Delete the documents of several notes with a single deletion task - async version
+pub async fn delete_notes_from_index_async(
+    vault_name: &str,
+    relpaths: &[String],
+) -> Result<(), Box<dyn Error>> {
+    let result = delete_documents_for_all(vault_name, relpaths).await;
+    metrics::record_index_operation("delete_note", &result);
+    result
+}
+
+/// Delete every document indexed under any of the given relpaths
+async fn delete_documents_for_all(
+    vault_name: &str,
+    relpaths: &[String],
+) -> Result<(), Box<dyn Error>> {
+    const PAGE_SIZE: usize = 1000;
+    let index = vault_index(vault_name).await?;
+    let mut ids = Vec::new();
+    for chunk in relpaths.chunks(100) {
+        let quoted: Vec<String> = chunk
+            .iter()
+            .map(|relpath| format!("'{}'", relpath.replace("'", "\\'")))
+            .collect();
+        let filter = format!("relpath IN [{}]", quoted.join(", "));
+        let mut fetched_for_chunk = 0;
+        loop {
+            let page = DocumentsQuery::new(&index)
+                .with_filter(&filter)
+                .with_fields(["id", "relpath"])
+                .with_limit(PAGE_SIZE)
+                .with_offset(fetched_for_chunk)
+                .execute::<IndexedNote>()
+                .await?;
+            let fetched = page.results.len();
+            fetched_for_chunk += fetched;
+            ids.extend(page.results.into_iter().map(|note| note.id));
+            if fetched < PAGE_SIZE {
+                break;
+            }
+        }
+    }
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    let task = index
+        .delete_documents(&ids)
+        .await?
+        .wait_for_completion(&CLIENT, None, Some(Duration::from_secs(60)))
+        .await?;
+    if task.is_failure() {
+        return Err(format!("Failed to delete notes: {:?}", task.unwrap_failure()).into());
+    }
+    Ok(())
+}
+
+/// Delete every document indexed under the given relpath
+async fn delete_documents_for(vault_name: &str, relpath: &str) -> Result<(), Box<dyn Error>> {
     // First we need to find the document by its relpath
-    let search_results = search_by_relpath_async(relpath).await?;
+    let search_results = search_by_relpath_async(vault_name, relpath).await?;
 
     if search_results.hits.is_empty() {
         // Note not found in index, nothing to delete
@@ -138,10 +429,10 @@ pub async fn delete_note_from_index_async(relpath: &str) -> Result<(), Box<dyn E
     }
 
     // Delete each document that matches the relpath
+    let index = vault_index(vault_name).await?;
     for doc in search_results.hits {
-        let task = CLIENT
-            .index(INDEX_NAME)
-            .delete_document(doc.id)
+        let task = index
+            .delete_document(&doc.id)
             .await?
             .wait_for_completion(&CLIENT, None, Some(Duration::from_secs(60)))
             .await?;
@@ -154,49 +445,789 @@ pub async fn delete_note_from_index_async(relpath: &str) -> Result<(), Box<dyn E
     Ok(())
 }
 
+/// Builds a filter matching documents that carry every one of `tags`.
+fn tag_filter(tags: &[String]) -> Option<String> {
+    let clauses: Vec<String> = tags
+        .iter()
+        .map(|tag| tag.trim().trim_start_matches('#'))
+        .filter(|tag| !tag.is_empty())
+        .map(|tag| {
+            format!(
+                "tags = \"{}\"",
+                tag.replace('\\', "\\\\").replace('"', "\\\"")
+            )
+        })
+        .collect();
+    if clauses.is_empty() {
+        None
+    } else {
+        Some(clauses.join(" AND "))
+    }
+}
+
+/// Number of words in a search snippet
+const SNIPPET_WORDS: usize = 30;
+
+/// Search results returned when no limit is given (MeiliSearch's own default)
+pub const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+/// Most search results returned for one request
+pub const MAX_SEARCH_LIMIT: usize = 100;
+
+/// Returns the marker placed where a snippet was cropped.
+///
+/// Configured with `NOTEMANCY_CROP_MARKER`, defaulting to "…" (MeiliSearch's own default).
+pub fn default_crop_marker() -> String {
+    std::env::var("NOTEMANCY_CROP_MARKER").unwrap_or_else(|_| "…".to_string())
+}
+
 /// Search notes by query string - async version
-pub async fn search_notes_async(query: &str) -> Result<SearchResult, Box<dyn Error>> {
-    let results = CLIENT
-        .index(INDEX_NAME)
-        .search()
+///
+/// Each hit carries a snippet of its content cropped around the match, with `crop_marker`
+/// where text was cut, and `snippets` holding one per matched field (title and content).
+/// Returns the `limit` hits after the first `offset`, limited to notes carrying all of
+/// `tags` when any are given.
+pub async fn search_notes_async(
+    vault_name: &str,
+    query: &str,
+    crop_marker: &str,
+    limit: usize,
+    offset: usize,
+    tags: &[String],
+) -> Result<SearchResult, Box<dyn Error>> {
+    metrics::SEARCH_QUERIES.inc();
+    let filter = tag_filter(tags);
+    let index = vault_index(vault_name).await?;
+    let mut search = index.search();
+    if let Some(filter) = &filter {
+        search.with_filter(filter);
+    }
+    let results = search
         .with_query(query)
+        .with_limit(limit)
+        .with_offset(offset)
+        .with_attributes_to_crop(Selectors::Some(&[("content", None), ("title", None)]))
+        .with_crop_length(SNIPPET_WORDS)
+        .with_crop_marker(crop_marker)
+        .with_show_matches_position(true)
         .execute::<NoteDocument>()
         .await?;
 
+    let hits: Vec<NoteDocument> = results
+        .hits
+        .into_iter()
+        .map(|hit| {
+            let formatted = |field: &str| {
+                hit.formatted_result
+                    .as_ref()
+                    .and_then(|formatted| formatted.get(field))
+                    .and_then(|value| value.as_str())
+                    .map(str::to_string)
+            };
+            let matched = |field: &str| {
+                hit.matches_position
+                    .as_ref()
+                    .is_some_and(|positions| positions.contains_key(field))
+            };
+            let cropped_title = formatted("title");
+            let cropped = formatted("content");
+            let (title_matched, content_matched) = (matched("title"), matched("content"));
+
+            let mut document = hit.result;
+            let content_snippet =
+                cropped.unwrap_or_else(|| extract_snippet(&document.content, query, crop_marker));
+            document.snippets = Some(Snippets {
+                title: title_matched
+                    .then(|| cropped_title.unwrap_or_else(|| document.title.clone())),
+                content: content_matched.then(|| content_snippet.clone()),
+            });
+            document.snippet = Some(content_snippet);
+            document
+        })
+        .collect();
     Ok(SearchResult {
-        hits_count: results.hits.len(),
-        hits: results.hits.into_iter().map(|hit| hit.result).collect(),
+        hits_count: hits.len(),
+        hits,
+        estimated_total_hits: results.estimated_total_hits,
     })
 }
 
+/// Number of notes matching a query, without fetching any of them - async version
+///
+/// MeiliSearch's count is an estimate for large result sets.
+pub async fn count_matches_async(vault_name: &str, query: &str) -> Result<usize, Box<dyn Error>> {
+    metrics::SEARCH_QUERIES.inc();
+    let index = vault_index(vault_name).await?;
+    let results = index
+        .search()
+        .with_query(query)
+        .with_limit(0)
+        .execute::<NoteDocument>()
+        .await?;
+    Ok(results
+        .estimated_total_hits
+        .or(results.total_hits)
+        .unwrap_or(0))
+}
+
+/// Crops `content` to a window of words around the first occurrence of a query term
+/// (or its beginning when no term occurs), marking each cut with `crop_marker`.
+///
+/// Used when MeiliSearch doesn't return a cropped version of the content.
+pub fn extract_snippet(content: &str, query: &str, crop_marker: &str) -> String {
+    let words: Vec<&str> = content.split_whitespace().collect();
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| term.to_lowercase())
+        .collect();
+    let first_match = words
+        .iter()
+        .position(|word| {
+            let word = word.to_lowercase();
+            terms.iter().any(|term| word.contains(term.as_str()))
+        })
+        .unwrap_or(0);
+
+    let start = first_match
+        .saturating_sub(SNIPPET_WORDS / 2)
+        .min(words.len().saturating_sub(SNIPPET_WORDS));
+    let end = (start + SNIPPET_WORDS).min(words.len());
+    let mut snippet = words[start..end].join(" ");
+    if start > 0 {
+        snippet = format!("{}{}", crop_marker, snippet);
+    }
+    if end < words.len() {
+        snippet.push_str(crop_marker);
+    }
+    snippet
+}
+
+/// Number of documents currently in the search index - async version
+pub async fn document_count_async(vault_name: &str) -> Result<usize, Box<dyn Error>> {
+    let index = vault_index(vault_name).await?;
+    let stats = index.get_stats().await?;
+    Ok(stats.number_of_documents)
+}
+
+/// The excluded terms without quotes (which can't be escaped in a phrase) or blanks
+fn excluded_phrases(terms: &[String]) -> Vec<String> {
+    terms
+        .iter()
+        .map(|term| term.replace('"', "").trim().to_string())
+        .filter(|term| !term.is_empty())
+        .collect()
+}
+
+/// The query with each excluded term appended as a negative phrase (`-"term"`), so
+/// MeiliSearch drops notes containing it before paging and counting the matches. Negative
+/// phrases need MeiliSearch 1.2 or later; see `without_excluded` for older servers.
+pub fn with_excluded_terms(query: &str, terms: &[String]) -> String {
+    excluded_phrases(terms)
+        .iter()
+        .fold(query.to_string(), |query, term| {
+            format!("{} -\"{}\"", query, term)
+        })
+}
+
+/// The hits whose title and content contain none of the excluded terms (compared
+/// case-insensitively). MeiliSearch 1.2 and later have already dropped them through
+/// `with_excluded_terms`; older servers ignore negative phrases, and this keeps their
+/// hits from including excluded notes.
+pub fn without_excluded(hits: Vec<NoteDocument>, terms: &[String]) -> Vec<NoteDocument> {
+    let terms: Vec<String> = excluded_phrases(terms)
+        .iter()
+        .map(|term| term.to_lowercase())
+        .collect();
+    hits.into_iter()
+        .filter(|hit| {
+            let (title, content) = (hit.title.to_lowercase(), hit.content.to_lowercase());
+            !terms
+                .iter()
+                .any(|term| title.contains(term.as_str()) || content.contains(term.as_str()))
+        })
+        .collect()
+}
+
+/// Group documents by folder, preserving relevance order within and across groups.
+///
+/// With `top_level` the first path component is used, otherwise the immediate parent folder.
+/// Notes at the vault root are grouped under "".
+pub fn group_by_folder(
+    hits: Vec<NoteDocument>,
+    top_level: bool,
+) -> Vec<(String, Vec<NoteDocument>)> {
+    let mut groups: Vec<(String, Vec<NoteDocument>)> = Vec::new();
+    for doc in hits {
+        let folder = match doc.relpath.rsplit_once('/') {
+            Some((parent, _)) if top_level => parent.split('/').next().unwrap_or("").to_string(),
+            Some((parent, _)) => parent.to_string(),
+            None => String::new(),
+        };
+        match groups.iter_mut().find(|(name, _)| *name == folder) {
+            Some((_, docs)) => docs.push(doc),
+            None => groups.push((folder, vec![doc])),
+        }
+    }
+    groups
+}
+
 /// Build the search index from all notes in the vault - async version
 pub async fn build_search_index_async(vault_name: &str) -> Result<(), Box<dyn Error>> {
     // Configure MeiliSearch first
-    configure_meilisearch_async().await?;
+    configure_meilisearch_async(vault_name).await?;
 
-    // Get all notes from the vault
-    let notes = notemancy_core::utils::list_notes(vault_name)?;
+    // Start from an empty index so a rebuild doesn't leave duplicate or stale documents
+    let task = CLIENT
+        .index(index_uid(vault_name))
+        .delete_all_documents()
+        .await?
+        .wait_for_completion(&CLIENT, None, Some(Duration::from_secs(60)))
+        .await?;
+    if task.is_failure() {
+        return Err(format!("Failed to clear index: {:?}", task.unwrap_failure()).into());
+    }
+
+    // Read the notes in parallel and index them a batch at a time, so a large vault is
+    // never held in memory at once. Notes that can't be read are logged and skipped.
+    let relpaths = crate::utils::list_note_relpaths(vault_name)?;
+    for batch in relpaths.chunks(INDEX_BATCH_SIZE) {
+        let documents: Vec<NoteDocument> = stream::iter(batch.to_vec())
+            .map(|relpath| {
+                let vault_name = vault_name.to_string();
+                rocket::tokio::task::spawn_blocking(move || {
+                    let document = note_document(&vault_name, &relpath).map_err(|e| e.to_string());
+                    (relpath, document)
+                })
+            })
+            .buffer_unordered(read_concurrency())
+            .filter_map(|read| async move {
+                match read {
+                    Ok((_, Ok(document))) => Some(document),
+                    Ok((relpath, Err(e))) => {
+                        eprintln!("Skipping '{}' while indexing: {}", relpath, e);
+                        None
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to read a note while indexing: {}", e);
+                        None
+                    }
+                }
+            })
+            .collect()
+            .await;
+        index_notes_async(vault_name, &documents).await?;
+    }
+
+    if index_attachments() {
+        let relpaths = attachment_relpaths(vault_name)?;
+        for batch in relpaths.chunks(INDEX_BATCH_SIZE) {
+            let documents: Vec<NoteDocument> = batch
+                .iter()
+                .map(|relpath| attachment_document(relpath))
+                .collect();
+            index_notes_async(vault_name, &documents).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Number of documents sent to MeiliSearch in one request when building the index
+const INDEX_BATCH_SIZE: usize = 500;
+
+/// Number of notes read at once when building the index, from
+/// `NOTEMANCY_INDEX_CONCURRENCY` (default: the number of CPUs).
+fn read_concurrency() -> usize {
+    std::env::var("NOTEMANCY_INDEX_CONCURRENCY")
+        .ok()
+        .and_then(|n| n.trim().parse().ok())
+        .filter(|&n| n > 0)
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4)
+        })
+}
+
+/// The fields of an indexed document needed to compare it with the vault
+#[derive(Deserialize)]
+struct IndexedNote {
+    id: String,
+    relpath: String,
+    #[serde(default)]
+    hash: String,
+}
+
+/// Fetch the id, relpath and hash of every document in a vault's index
+async fn indexed_notes(vault_name: &str) -> Result<Vec<IndexedNote>, Box<dyn Error>> {
+    const PAGE_SIZE: usize = 1000;
+    let index = vault_index(vault_name).await?;
+    let mut notes = Vec::new();
+    loop {
+        let page = DocumentsQuery::new(&index)
+            .with_fields(["id", "relpath", "hash"])
+            .with_limit(PAGE_SIZE)
+            .with_offset(notes.len())
+            .execute::<IndexedNote>()
+            .await?;
+        let fetched = page.results.len();
+        notes.extend(page.results);
+        if fetched < PAGE_SIZE {
+            return Ok(notes);
+        }
+    }
+}
+
+/// What `repair_index_async` changed, by relpath
+#[derive(Serialize, Debug, Default)]
+pub struct RepairReport {
+    /// Notes on disk that were missing from the index
+    pub added: Vec<String>,
+    /// Indexed notes that no longer exist on disk
+    pub deleted: Vec<String>,
+    /// Notes whose contents changed since they were indexed (or that were indexed twice)
+    pub updated: Vec<String>,
+    /// Notes that couldn't be read, left as they are in the index
+    pub failed: Vec<RepairFailure>,
+}
+
+/// A note `repair_index_async` skipped
+#[derive(Serialize, Debug)]
+pub struct RepairFailure {
+    pub relpath: String,
+    pub error: String,
+}
+
+/// Reconcile the search index with the vault in one pass - async version
+///
+/// Notes missing from the index are added, documents for notes that no longer exist are
+/// deleted, and documents whose content hash no longer matches the file are replaced. Notes
+/// that can't be read are reported under `failed` without stopping the repair.
+pub async fn repair_index_async(vault_name: &str) -> Result<RepairReport, Box<dyn Error>> {
+    let mut indexed: HashMap<String, Vec<IndexedNote>> = HashMap::new();
+    for note in indexed_notes(vault_name).await? {
+        indexed.entry(note.relpath.clone()).or_default().push(note);
+    }
 
-    // Create documents for each note
+    let mut report = RepairReport::default();
+    let mut stale_ids = Vec::new();
     let mut documents = Vec::new();
-    for note_info in notes {
-        let content = notemancy_core::crud::read_note(vault_name, &note_info.relpath, false)?;
-        let document = NoteDocument {
-            id: COUNTER.fetch_add(1, Ordering::SeqCst),
-            relpath: note_info.relpath.clone(),
-            title: note_info.title,
-            content,
+    for relpath in crate::utils::list_note_relpaths(vault_name)? {
+        let existing = indexed.remove(&relpath);
+        let document = match note_document(vault_name, &relpath) {
+            Ok(document) => document,
+            Err(e) => {
+                eprintln!("Skipping '{}' while repairing the index: {}", relpath, e);
+                report.failed.push(RepairFailure {
+                    relpath,
+                    error: e.to_string(),
+                });
+                continue;
+            }
         };
+        match existing {
+            None => report.added.push(relpath),
+            Some(existing) => {
+                if existing.len() == 1
+                    && existing[0].hash == document.hash
+                    && existing[0].id == document.id
+                {
+                    continue;
+                }
+                stale_ids.extend(
+                    existing
+                        .into_iter()
+                        .map(|note| note.id)
+                        .filter(|id| *id != document.id),
+                );
+                report.updated.push(relpath);
+            }
+        }
         documents.push(document);
     }
+    if index_attachments() {
+        for relpath in attachment_relpaths(vault_name)? {
+            // Attachments are indexed by path only, so an existing document never goes stale.
+            if indexed.remove(&relpath).is_none() {
+                documents.push(attachment_document(&relpath));
+                report.added.push(relpath);
+            }
+        }
+    }
+    for (relpath, notes) in indexed {
+        stale_ids.extend(notes.into_iter().map(|note| note.id));
+        report.deleted.push(relpath);
+    }
+    report.deleted.sort();
 
-    // Index all documents
-    index_notes_async(&documents).await?;
+    if !stale_ids.is_empty() {
+        let task = CLIENT
+            .index(index_uid(vault_name))
+            .delete_documents(&stale_ids)
+            .await?
+            .wait_for_completion(&CLIENT, None, Some(Duration::from_secs(60)))
+            .await?;
+        let result: Result<(), Box<dyn Error>> = if task.is_failure() {
+            Err(format!("Failed to delete notes: {:?}", task.unwrap_failure()).into())
+        } else {
+            Ok(())
+        };
+        metrics::record_index_operation("delete_note", &result);
+        result?;
+    }
+    index_notes_async(vault_name, &documents).await?;
 
-    Ok(())
+    Ok(report)
+}
+
+/// Health of the search index compared with the vault
+#[derive(Serialize, Debug)]
+pub struct IndexHealth {
+    /// Number of documents in the index
+    pub document_count: usize,
+    /// Number of notes on disk
+    pub note_count: usize,
+    /// Number of attachments on disk, when they are indexed (see `index_attachments`)
+    pub attachment_count: usize,
+    /// Whether the document count differs from the number of notes and attachments
+    pub drift: bool,
+    /// Whether MeiliSearch is currently processing updates
+    pub is_indexing: bool,
+    /// Number of documents containing each field
+    pub field_distribution: BTreeMap<String, usize>,
+    /// When the index was last updated (RFC3339)
+    pub last_update: Option<String>,
+}
+
+/// Fetch the index stats and compare them with the notes on disk - async version
+pub async fn index_health_async(vault_name: &str) -> Result<IndexHealth, Box<dyn Error>> {
+    vault_index(vault_name).await?;
+    let index = CLIENT.get_index(index_uid(vault_name)).await?;
+    let stats = index.get_stats().await?;
+    let note_count = crate::utils::list_note_relpaths(vault_name)?.len();
+    let attachment_count = if index_attachments() {
+        attachment_relpaths(vault_name)?.len()
+    } else {
+        0
+    };
+    let last_update = index
+        .updated_at
+        .and_then(|time| DateTime::<Utc>::from_timestamp(time.unix_timestamp(), time.nanosecond()))
+        .map(|time| time.to_rfc3339());
+
+    Ok(IndexHealth {
+        document_count: stats.number_of_documents,
+        note_count,
+        attachment_count,
+        drift: stats.number_of_documents != note_count + attachment_count,
+        is_indexing: stats.is_indexing,
+        field_distribution: stats.field_distribution.into_iter().collect(),
+        last_update,
+    })
 }
 
-/// Get a new unique ID for a document
-pub fn get_new_id() -> usize {
-    COUNTER.fetch_add(1, Ordering::SeqCst)
+/// Hash raw note contents (64-bit FNV-1a, as hex)
+pub fn content_hash(raw: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in raw.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// How search document ids are assigned. Either way a note gets the same id every time it
+/// is indexed, so reindexing it replaces its own document and never another note's, even
+/// in an index that outlived a restart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IdScheme {
+    /// An id derived from the note's relpath
+    Relpath,
+    /// The note's frontmatter `id` (or `uuid`), falling back to one derived from its
+    /// relpath. A renamed note keeps its document as long as its frontmatter id is set.
+    Frontmatter,
+}
+
+/// Returns the id scheme, set with `NOTEMANCY_ID_SCHEME=frontmatter|relpath` (`counter`,
+/// the numbered ids of earlier versions, is read as `relpath`).
+///
+/// Defaults to `frontmatter`. Only the default vault's index is rebuilt at startup; running
+/// `/notes/index/repair` on a vault replaces every document whose id no longer matches,
+/// such as those indexed under another scheme (see the readme). Notes sharing a frontmatter
+/// id overwrite each other in the index.
+pub fn id_scheme() -> IdScheme {
+    match std::env::var("NOTEMANCY_ID_SCHEME")
+        .as_deref()
+        .map(str::trim)
+    {
+        Ok("relpath" | "counter") => IdScheme::Relpath,
+        _ => IdScheme::Frontmatter,
+    }
+}
+
+/// Whether a string can be used as a MeiliSearch document id as-is (ASCII alphanumerics,
+/// `-` and `_`, at most 511 bytes).
+fn is_valid_document_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 511
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Picks the document id for a note under the configured `IdScheme`.
+pub fn document_id(relpath: &str, frontmatter: &serde_json::Value) -> String {
+    document_id_with(id_scheme(), relpath, frontmatter)
+}
+
+/// Picks the document id for a note under `scheme`. Frontmatter ids that MeiliSearch
+/// wouldn't accept are hashed, as are relpaths (which contain `/` and `.`).
+fn document_id_with(scheme: IdScheme, relpath: &str, frontmatter: &serde_json::Value) -> String {
+    if scheme == IdScheme::Relpath {
+        return format!("path-{}", content_hash(relpath));
+    }
+    let from_frontmatter = ["id", "uuid"]
+        .iter()
+        .find_map(|key| match frontmatter.get(key) {
+            Some(serde_json::Value::String(id)) if !id.trim().is_empty() => {
+                Some(id.trim().to_string())
+            }
+            Some(serde_json::Value::Number(id)) => Some(id.to_string()),
+            _ => None,
+        });
+    match from_frontmatter {
+        Some(id) if is_valid_document_id(&id) => id,
+        Some(id) => format!("id-{}", content_hash(&id)),
+        None => format!("path-{}", content_hash(relpath)),
+    }
+}
+
+/// Build the search document for a note from its current contents on disk
+///
+/// The relpath is stored with its on-disk case (see `utils::canonical_relpath`).
+pub fn note_document(vault_name: &str, relpath: &str) -> Result<NoteDocument, Box<dyn Error>> {
+    let vault_dir = crate::paths::vault_root(vault_name)?;
+    let relpath = &crate::utils::canonical_relpath(&vault_dir, relpath);
+    let raw = notemancy_core::crud::read_note(vault_name, relpath, true)?;
+    let (frontmatter, content) = frontmatter::parse_frontmatter(&raw);
+    let title = title::resolve_title(relpath, &frontmatter, &content, &title::title_order());
+    Ok(NoteDocument {
+        id: document_id(relpath, &frontmatter),
+        relpath: relpath.to_string(),
+        kind: DocumentKind::Note,
+        title,
+        tags: crate::tags::note_tags(&frontmatter),
+        content,
+        metadata: frontmatter_text(&frontmatter, &indexed_frontmatter_fields()),
+        hash: content_hash(&raw),
+        snippet: None,
+        snippets: None,
+        first_match_line: None,
+    })
+}
+
+/// Whether attachment file names are indexed alongside notes, set with
+/// `NOTEMANCY_INDEX_ATTACHMENTS=1`.
+pub fn index_attachments() -> bool {
+    std::env::var("NOTEMANCY_INDEX_ATTACHMENTS")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Lists the relative paths of every non-note file in the vault.
+fn attachment_relpaths(vault_name: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    Ok(crate::utils::list_file_relpaths(vault_name)?
+        .into_iter()
+        .filter(|relpath| !crate::paths::is_note_file(std::path::Path::new(relpath)))
+        .collect())
+}
+
+/// Build the search document for an attachment: its file name as the title, and no content.
+pub fn attachment_document(relpath: &str) -> NoteDocument {
+    let title = relpath.rsplit('/').next().unwrap_or(relpath).to_string();
+    NoteDocument {
+        id: document_id(relpath, &serde_json::Value::Null),
+        relpath: relpath.to_string(),
+        kind: DocumentKind::Attachment,
+        title,
+        content: String::new(),
+        tags: Vec::new(),
+        metadata: String::new(),
+        hash: String::new(),
+        snippet: None,
+        snippets: None,
+        first_match_line: None,
+    }
+}
+
+/// Returns the frontmatter fields whose values are indexed as searchable text.
+///
+/// Configured with `NOTEMANCY_INDEX_FRONTMATTER_FIELDS`: a comma-separated list of keys,
+/// or `1`/`true` for title, tags, aliases and summary. Unset indexes no frontmatter.
+pub fn indexed_frontmatter_fields() -> Vec<String> {
+    let configured = std::env::var("NOTEMANCY_INDEX_FRONTMATTER_FIELDS").unwrap_or_default();
+    match configured.trim() {
+        "" | "0" | "false" => Vec::new(),
+        "1" | "true" => ["title", "tags", "aliases", "summary"]
+            .iter()
+            .map(|field| field.to_string())
+            .collect(),
+        fields => fields
+            .split(',')
+            .map(|field| field.trim().to_string())
+            .filter(|field| !field.is_empty())
+            .collect(),
+    }
+}
+
+/// Flattens the given frontmatter fields into plain text, one field per line.
+fn frontmatter_text(frontmatter: &serde_json::Value, fields: &[String]) -> String {
+    fn flatten(value: &serde_json::Value) -> String {
+        match value {
+            serde_json::Value::String(text) => text.clone(),
+            serde_json::Value::Array(items) => items
+                .iter()
+                .map(flatten)
+                .filter(|text| !text.is_empty())
+                .collect::<Vec<_>>()
+                .join(" "),
+            serde_json::Value::Null | serde_json::Value::Object(_) => String::new(),
+            other => other.to_string(),
+        }
+    }
+
+    fields
+        .iter()
+        .filter_map(|field| frontmatter.get(field))
+        .map(flatten)
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Replace a note's search document with one built from its current contents - async version
+pub async fn reindex_note_async(vault_name: &str, relpath: &str) -> Result<(), Box<dyn Error>> {
+    let vault_dir = crate::paths::vault_root(vault_name)?;
+    let relpath = crate::utils::canonical_relpath(&vault_dir, relpath);
+    move_note_in_index_async(vault_name, &relpath, &relpath).await
+}
+
+/// Re-index a note that moved from one relpath to another - async version
+pub async fn move_note_in_index_async(
+    vault_name: &str,
+    from: &str,
+    to: &str,
+) -> Result<(), Box<dyn Error>> {
+    delete_note_from_index_async(vault_name, from).await?;
+    let document = note_document(vault_name, to)?;
+    index_note_async(vault_name, &document).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ranking itself happens in MeiliSearch. This checks the settings every index is
+    // configured with: the `attribute` rule ranks a match by the searchable attribute it is
+    // in, and `title` comes before `content`.
+    #[test]
+    fn index_settings_rank_title_matches_above_body_matches() {
+        let settings = index_settings().unwrap();
+        let attributes = settings.searchable_attributes.unwrap();
+        let position = |field: &str| attributes.iter().position(|f| f == field).unwrap();
+        assert!(position("title") < position("content"));
+        let rules = settings.ranking_rules.unwrap();
+        assert!(rules.iter().any(|rule| rule == "attribute"));
+    }
+
+    #[test]
+    fn configured_attribute_order_is_kept() {
+        assert_eq!(
+            parse_searchable_attributes("content, title, unknown, title"),
+            vec!["content", "title"]
+        );
+    }
+
+    #[test]
+    fn excluded_terms_become_negative_phrases() {
+        let excluded = vec!["cancelled".to_string(), "team offsite".to_string()];
+        assert_eq!(
+            with_excluded_terms("meeting", &excluded),
+            "meeting -\"cancelled\" -\"team offsite\""
+        );
+    }
+
+    #[test]
+    fn excluded_terms_drop_quotes_and_blanks() {
+        let excluded = vec!["\"draft\"".to_string(), " ".to_string()];
+        assert_eq!(with_excluded_terms("notes", &excluded), "notes -\"draft\"");
+        assert_eq!(with_excluded_terms("notes", &[]), "notes");
+    }
+
+    fn note(relpath: &str, title: &str, content: &str) -> NoteDocument {
+        NoteDocument {
+            id: document_id_with(IdScheme::Relpath, relpath, &serde_json::Value::Null),
+            relpath: relpath.to_string(),
+            kind: DocumentKind::Note,
+            title: title.to_string(),
+            content: content.to_string(),
+            tags: Vec::new(),
+            metadata: String::new(),
+            hash: String::new(),
+            snippet: None,
+            snippets: None,
+            first_match_line: None,
+        }
+    }
+
+    #[test]
+    fn excluded_terms_remove_matching_notes() {
+        let hits = vec![
+            note("weekly.md", "Weekly meeting", "Agenda for the meeting"),
+            note("offsite.md", "Meeting", "The meeting was Cancelled."),
+            note("retro.md", "Cancelled: retro meeting", "Moved to next week"),
+            note("team.md", "Team meeting", "Planning the team offsite"),
+        ];
+        let excluded = vec!["cancelled".to_string(), "\"team offsite\"".to_string()];
+        let relpaths: Vec<String> = without_excluded(hits, &excluded)
+            .into_iter()
+            .map(|hit| hit.relpath)
+            .collect();
+        assert_eq!(relpaths, vec!["weekly.md"]);
+    }
+
+    #[test]
+    fn no_excluded_terms_keep_every_hit() {
+        let hits = vec![note("a.md", "A", "text"), note("b.md", "B", "more text")];
+        assert_eq!(without_excluded(hits, &[" ".to_string()]).len(), 2);
+    }
+
+    #[test]
+    fn reindexing_after_a_restart_gives_each_note_its_own_id() {
+        let relpaths = ["a.md", "projects/a.md", "b.md", "photo.png"];
+        let pass = |scheme| {
+            relpaths
+                .iter()
+                .map(|relpath| document_id_with(scheme, relpath, &serde_json::Value::Null))
+                .collect::<Vec<_>>()
+        };
+        for scheme in [IdScheme::Relpath, IdScheme::Frontmatter] {
+            let before = pass(scheme);
+            // Nothing carries over between the two passes, as across a restart.
+            let after = pass(scheme);
+            assert_eq!(before, after);
+            assert_eq!(before.iter().collect::<HashSet<_>>().len(), relpaths.len());
+            assert!(before.iter().all(|id| is_valid_document_id(id)));
+        }
+    }
+
+    #[test]
+    fn each_vault_has_its_own_index() {
+        assert_eq!(index_uid("main"), "notes-main");
+        assert_ne!(index_uid("work"), index_uid("personal"));
+        // Characters MeiliSearch rejects in uids are hashed away.
+        let uid = index_uid("my vault/2024");
+        assert!(is_valid_document_id(&uid));
+        assert_ne!(uid, index_uid("my vault/2025"));
+    }
 }
@@ -0,0 +1,127 @@
+use chrono::Local;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::{Deserialize, Serialize, json::Json};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Serializes appends so concurrent searches don't interleave lines
+static LOG_LOCK: Mutex<()> = Mutex::new(());
+
+/// Whether search queries are logged, set with `NOTEMANCY_LOG_SEARCHES=1`. Off by default.
+pub fn enabled() -> bool {
+    std::env::var("NOTEMANCY_LOG_SEARCHES")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// The append-only log file: `NOTEMANCY_SEARCH_LOG` if set, otherwise `search-log.jsonl` in
+/// `NOTEMANCY_CONF_DIR` (or the working directory).
+pub fn log_path() -> PathBuf {
+    if let Ok(path) = std::env::var("NOTEMANCY_SEARCH_LOG") {
+        return PathBuf::from(path);
+    }
+    std::env::var("NOTEMANCY_CONF_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_default()
+        .join("search-log.jsonl")
+}
+
+/// One logged search. Only the query text is kept, nothing about the client.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SearchLogEntry {
+    pub query: String,
+    pub results: usize,
+    /// When the search ran (RFC3339)
+    pub timestamp: String,
+}
+
+/// Appends a search to the log when logging is enabled. Queries are trimmed and lowercased
+/// so repeated searches are counted together.
+pub fn record(query: &str, results: usize) -> Result<(), Box<dyn Error>> {
+    if !enabled() {
+        return Ok(());
+    }
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Ok(());
+    }
+    let entry = SearchLogEntry {
+        query,
+        results,
+        timestamp: Local::now().to_rfc3339(),
+    };
+    let line = serde_json::to_string(&entry)?;
+
+    let _guard = LOG_LOCK.lock().unwrap();
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path())?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct QueryCount {
+    pub query: String,
+    pub count: usize,
+    /// Result count of the most recent run of this query
+    pub last_results: usize,
+    pub last_searched: String,
+}
+
+/// Reads the log and returns the `limit` most frequent queries, most frequent first.
+/// Malformed lines are skipped.
+pub fn top_queries(limit: usize) -> Result<Vec<QueryCount>, Box<dyn Error>> {
+    let content = match fs::read_to_string(log_path()) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut counts: HashMap<String, QueryCount> = HashMap::new();
+    for entry in content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<SearchLogEntry>(line).ok())
+    {
+        let count = counts
+            .entry(entry.query.clone())
+            .or_insert_with(|| QueryCount {
+                query: entry.query,
+                count: 0,
+                last_results: 0,
+                last_searched: String::new(),
+            });
+        count.count += 1;
+        count.last_results = entry.results;
+        count.last_searched = entry.timestamp;
+    }
+
+    let mut top: Vec<QueryCount> = counts.into_values().collect();
+    top.sort_by(|a, b| b.count.cmp(&a.count).then(a.query.cmp(&b.query)));
+    top.truncate(limit);
+    Ok(top)
+}
+
+/// The most frequent search queries. `limit` defaults to 20.
+///
+/// Responds with 404 when search logging is disabled.
+#[get("/notes/search/top?<limit>")]
+pub fn top_searches(limit: Option<usize>) -> Result<Json<Vec<QueryCount>>, status::Custom<String>> {
+    if !enabled() {
+        return Err(status::Custom(
+            Status::NotFound,
+            "Search logging is disabled; set NOTEMANCY_LOG_SEARCHES=1 to enable it".to_string(),
+        ));
+    }
+    top_queries(limit.unwrap_or(20))
+        .map(Json)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))
+}
@@ -0,0 +1,79 @@
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::ContentType;
+use rocket::{Request, Response};
+use serde_json::Value;
+use std::io::Cursor;
+
+/// Keeps only the requested fields of every object found inside an array.
+///
+/// Wrapper objects (e.g. `{ total, results }`) are left intact so the projection reaches
+/// the list items, and `children` arrays are always kept so trees stay navigable.
+pub fn project_fields(value: &mut Value, fields: &[&str], in_array: bool) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                project_fields(item, fields, true);
+            }
+        }
+        Value::Object(map) => {
+            if in_array {
+                map.retain(|key, _| key == "children" || fields.contains(&key.as_str()));
+            }
+            for child in map.values_mut() {
+                project_fields(child, fields, false);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Fairing that reshapes JSON responses on request: `?pretty=1` pretty-prints the body and
+/// `?fields=relpath,title` projects list items onto the given fields.
+pub struct ShapeJson;
+
+#[rocket::async_trait]
+impl Fairing for ShapeJson {
+    fn info(&self) -> Info {
+        Info {
+            name: "Pretty-print and project JSON responses",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, req: &'r Request<'_>, res: &mut Response<'r>) {
+        let pretty = matches!(req.query_value::<&str>("pretty"), Some(Ok("1" | "true")));
+        let fields: Vec<&str> = req
+            .query_value::<&str>("fields")
+            .and_then(Result::ok)
+            .map(|fields| {
+                fields
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|f| !f.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if (!pretty && fields.is_empty()) || res.content_type() != Some(ContentType::JSON) {
+            return;
+        }
+
+        let Ok(body) = res.body_mut().to_string().await else {
+            return;
+        };
+        let shaped = match serde_json::from_str::<Value>(&body) {
+            Ok(mut value) => {
+                if !fields.is_empty() {
+                    project_fields(&mut value, &fields, false);
+                }
+                let rendered = if pretty {
+                    serde_json::to_string_pretty(&value)
+                } else {
+                    serde_json::to_string(&value)
+                };
+                rendered.unwrap_or(body)
+            }
+            Err(_) => body,
+        };
+        res.set_sized_body(shaped.len(), Cursor::new(shaped));
+    }
+}
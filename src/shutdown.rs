@@ -0,0 +1,40 @@
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::figment::Figment;
+use rocket::{Orbit, Rocket};
+
+/// Seconds in-flight requests get to finish once shutdown begins, from
+/// `SHUTDOWN_GRACE_SECS` (default 5).
+pub fn grace_secs() -> u32 {
+    std::env::var("SHUTDOWN_GRACE_SECS")
+        .ok()
+        .and_then(|secs| secs.trim().parse().ok())
+        .unwrap_or(5)
+}
+
+/// Rocket's configuration with graceful shutdown on Ctrl-C (and SIGTERM on Unix) using the
+/// configured grace period.
+pub fn figment() -> Figment {
+    rocket::Config::figment()
+        .merge(("shutdown.ctrlc", true))
+        .merge(("shutdown.grace", grace_secs()))
+}
+
+/// Fairing that logs when shutdown begins
+pub struct LogShutdown;
+
+#[rocket::async_trait]
+impl Fairing for LogShutdown {
+    fn info(&self) -> Info {
+        Info {
+            name: "Log shutdown",
+            kind: Kind::Shutdown,
+        }
+    }
+
+    async fn on_shutdown(&self, rocket: &Rocket<Orbit>) {
+        println!(
+            "Shutting down; waiting up to {} seconds for in-flight requests",
+            rocket.config().shutdown.grace
+        );
+    }
+}
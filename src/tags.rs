@@ -0,0 +1,490 @@
+use chrono::{DateTime, Local};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::{Deserialize, Serialize, json::Json};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::time::SystemTime;
+
+use crate::access::WriteAccess;
+use crate::errors::JsonBody;
+use crate::utils::{self, NoteRecord};
+use crate::vault::Vault;
+use crate::{cache, frontmatter, paths, search};
+
+/// Returns the tags listed in a note's frontmatter.
+///
+/// `tags` may be a list or a single string separated by commas or whitespace. Leading `#`s
+/// are stripped and duplicates dropped, keeping the first occurrence's order.
+pub fn note_tags(frontmatter: &Value) -> Vec<String> {
+    let raw: Vec<String> = match frontmatter.get("tags") {
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| match item {
+                Value::String(tag) => Some(tag.clone()),
+                Value::Number(n) => Some(n.to_string()),
+                _ => None,
+            })
+            .collect(),
+        Some(Value::String(tags)) => tags
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .map(|tag| tag.to_string())
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in raw {
+        let tag = tag.trim().trim_start_matches('#').to_string();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+#[derive(Serialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct TagStats {
+    pub tag: String,
+    /// Number of notes carrying the tag
+    pub count: usize,
+    /// Last modification time (RFC3339) of the most recently edited note with the tag
+    pub latest_modified: Option<String>,
+}
+
+/// Counts notes per tag, most used first (ties broken alphabetically).
+pub fn tag_stats(notes: &[NoteRecord]) -> Vec<TagStats> {
+    let mut by_tag: HashMap<String, (usize, Option<SystemTime>)> = HashMap::new();
+    for note in notes {
+        for tag in note_tags(&note.frontmatter) {
+            let entry = by_tag.entry(tag).or_insert((0, None));
+            entry.0 += 1;
+            entry.1 = entry.1.max(note.modified);
+        }
+    }
+
+    let mut stats: Vec<TagStats> = by_tag
+        .into_iter()
+        .map(|(tag, (count, latest))| TagStats {
+            tag,
+            count,
+            latest_modified: latest.map(|time| DateTime::<Local>::from(time).to_rfc3339()),
+        })
+        .collect();
+    stats.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+    stats
+}
+
+/// Usage count and recency of every tag in the vault.
+#[get("/notes/stats/tags")]
+pub fn stats_by_tag(vault: Vault) -> Result<Json<Vec<TagStats>>, status::Custom<String>> {
+    let stats = cache::get_or_compute(vault.name(), "tag_stats", || {
+        utils::load_notes(vault.name()).map(|notes| tag_stats(&notes))
+    })
+    .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    Ok(Json(stats.as_ref().clone()))
+}
+
+/// Whether the characters of `query` appear in `tag` in order (e.g. "prj" in "project").
+fn is_subsequence(query: &str, tag: &str) -> bool {
+    let mut chars = tag.chars();
+    query.chars().all(|q| chars.any(|c| c == q))
+}
+
+/// Picks the tags matching a prefix, case-insensitively: tags starting with it first, then
+/// tags containing its characters in order, each group by usage count.
+pub fn suggest_tags(stats: &[TagStats], prefix: &str, limit: usize) -> Vec<TagStats> {
+    let prefix = prefix.trim().trim_start_matches('#').to_lowercase();
+    let mut matches: Vec<(bool, &TagStats)> = stats
+        .iter()
+        .filter_map(|stat| {
+            let tag = stat.tag.to_lowercase();
+            if tag.starts_with(&prefix) {
+                Some((true, stat))
+            } else if is_subsequence(&prefix, &tag) {
+                Some((false, stat))
+            } else {
+                None
+            }
+        })
+        .collect();
+    // `stats` is already ordered by count, and the sort is stable.
+    matches.sort_by_key(|(is_prefix, _)| !is_prefix);
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, stat)| stat.clone())
+        .collect()
+}
+
+/// Existing tags matching what's been typed so far, for autocompletion. `limit` defaults
+/// to 10.
+#[get("/notes/tags/suggest?<prefix>&<limit>")]
+pub fn suggest(
+    vault: Vault,
+    prefix: String,
+    limit: Option<usize>,
+) -> Result<Json<Vec<TagStats>>, status::Custom<String>> {
+    let stats = cache::get_or_compute(vault.name(), "tag_stats", || {
+        utils::load_notes(vault.name()).map(|notes| tag_stats(&notes))
+    })
+    .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    Ok(Json(suggest_tags(&stats, &prefix, limit.unwrap_or(10))))
+}
+
+/// Renames a tag in a parsed YAML frontmatter block, returning whether it changed.
+///
+/// Handles `tags` as a list or as a comma/whitespace-separated string. When the note
+/// already has `to`, the renamed tag is dropped rather than duplicated.
+pub fn rename_tag(frontmatter: &mut serde_yaml::Value, from: &str, to: &str) -> bool {
+    let Some(tags) = frontmatter.get_mut("tags") else {
+        return false;
+    };
+    let matches = |tag: &str| tag.trim().trim_start_matches('#') == from;
+
+    match tags {
+        serde_yaml::Value::Sequence(items) => {
+            if !items.iter().any(|item| item.as_str().is_some_and(matches)) {
+                return false;
+            }
+            let mut renamed: Vec<serde_yaml::Value> = Vec::new();
+            for item in items.drain(..) {
+                let item = match item.as_str() {
+                    Some(tag) if matches(tag) => serde_yaml::Value::String(to.to_string()),
+                    _ => item,
+                };
+                let tag = item.as_str().map(|tag| tag.trim().trim_start_matches('#'));
+                let duplicate = tag.is_some()
+                    && renamed
+                        .iter()
+                        .any(|kept| kept.as_str().map(|t| t.trim().trim_start_matches('#')) == tag);
+                if !duplicate {
+                    renamed.push(item);
+                }
+            }
+            *items = renamed;
+            true
+        }
+        serde_yaml::Value::String(text) => {
+            let tokens: Vec<&str> = text
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|token| !token.is_empty())
+                .collect();
+            if !tokens.iter().any(|token| matches(token)) {
+                return false;
+            }
+            let mut renamed: Vec<&str> = Vec::new();
+            for token in tokens {
+                let token = if matches(token) { to } else { token };
+                if !renamed.contains(&token) {
+                    renamed.push(token);
+                }
+            }
+            let separator = if text.contains(',') { ", " } else { " " };
+            *text = renamed.join(separator);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Replaces inline `#from` tags in a note body with `#to`, skipping fenced code blocks.
+///
+/// A tag only matches when preceded by whitespace (or the line start) and not followed by
+/// more tag characters, so `#from-2` and `issue#from` are left alone.
+pub fn rename_inline_tag(body: &str, from: &str, to: &str) -> String {
+    let needle = format!("#{}", from);
+    let is_tag_char = |c: char| c.is_alphanumeric() || matches!(c, '-' | '_' | '/');
+    let mut in_code_block = false;
+    let mut lines = Vec::new();
+
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block || !line.contains(&needle) {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        let mut renamed = String::with_capacity(line.len());
+        let mut rest = line;
+        while let Some(pos) = rest.find(&needle) {
+            let before_ok = match rest[..pos].chars().next_back() {
+                Some(c) => c.is_whitespace(),
+                None => renamed.chars().next_back().is_none_or(char::is_whitespace),
+            };
+            let after = &rest[pos + needle.len()..];
+            let after_ok = !after.chars().next().is_some_and(is_tag_char);
+            renamed.push_str(&rest[..pos]);
+            if before_ok && after_ok {
+                renamed.push('#');
+                renamed.push_str(to);
+            } else {
+                renamed.push_str(&needle);
+            }
+            rest = after;
+        }
+        renamed.push_str(rest);
+        lines.push(renamed);
+    }
+    lines.concat()
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RenameTagRequest {
+    /// The tag to rename (a leading `#` is ignored)
+    pub from: String,
+    /// Its new name
+    pub to: String,
+    /// Also rewrite inline `#from` tags in note bodies
+    #[serde(default)]
+    pub inline: bool,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RenamedTag {
+    /// Number of notes that were rewritten
+    pub changed: usize,
+    /// The notes that were rewritten
+    pub relpaths: Vec<String>,
+}
+
+/// Rename a tag across the vault, rewriting the frontmatter (and optionally the inline tags)
+/// of every note that uses it, then reindex the changed notes.
+#[post("/notes/tags/rename", data = "<req>")]
+pub async fn rename_tag_route(
+    _write: WriteAccess,
+    vault: Vault,
+    req: JsonBody<RenameTagRequest>,
+) -> Result<Json<RenamedTag>, status::Custom<String>> {
+    let from = req.from.trim().trim_start_matches('#');
+    let to = req.to.trim().trim_start_matches('#');
+    if from.is_empty() || to.is_empty() || to.contains(|c: char| c == ',' || c.is_whitespace()) {
+        return Err(status::Custom(
+            Status::BadRequest,
+            "Tags must be non-empty and can't contain commas or whitespace".to_string(),
+        ));
+    }
+
+    let vault_dir = paths::vault_root(vault.name())
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    let relpaths = utils::list_note_relpaths(vault.name())
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+
+    let mut changed = Vec::new();
+    for relpath in relpaths {
+        let path = vault_dir.join(&relpath);
+        let Ok(raw) = fs::read_to_string(&path) else {
+            continue;
+        };
+        // `head` is the frontmatter block exactly as written, delimiters included.
+        let (mut yaml, head, body) = match frontmatter::split(&raw) {
+            Some((yaml, body)) => (
+                serde_yaml::from_str::<serde_yaml::Value>(yaml).ok(),
+                &raw[..raw.len() - body.len()],
+                body,
+            ),
+            None => (None, "", raw.as_str()),
+        };
+
+        let body = if req.inline {
+            rename_inline_tag(body, from, to)
+        } else {
+            body.to_string()
+        };
+        let renamed = yaml.as_mut().is_some_and(|yaml| rename_tag(yaml, from, to));
+        let updated = match yaml {
+            Some(yaml) if renamed => frontmatter::render(&yaml, &body)
+                .map_err(|e| status::Custom(Status::InternalServerError, e))?,
+            // Keep the original frontmatter text untouched when only the body changed.
+            _ => format!("{}{}", head, body),
+        };
+
+        if updated != raw {
+            fs::write(&path, &updated)
+                .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+            changed.push(relpath);
+        }
+    }
+
+    for relpath in &changed {
+        if let Err(e) = search::reindex_note_async(vault.name(), relpath)
+            .await
+            .map_err(|e| e.to_string())
+        {
+            eprintln!("Failed to reindex '{}': {}", relpath, e);
+        }
+    }
+    Ok(Json(RenamedTag {
+        changed: changed.len(),
+        relpaths: changed,
+    }))
+}
+
+/// Adds and removes tags from a list, keeping the existing order and dropping duplicates.
+/// Removals win over additions.
+pub fn apply_tag_changes(tags: &[String], add: &[String], remove: &[String]) -> Vec<String> {
+    let mut updated: Vec<String> = Vec::new();
+    for tag in tags.iter().chain(add) {
+        if !remove.contains(tag) && !updated.contains(tag) {
+            updated.push(tag.clone());
+        }
+    }
+    updated
+}
+
+/// Trims whitespace and leading `#`s from tag names, rejecting empty ones and ones
+/// containing commas or whitespace.
+fn normalize_tags(tags: &[String]) -> Result<Vec<String>, String> {
+    tags.iter()
+        .map(|tag| {
+            let tag = tag.trim().trim_start_matches('#');
+            if tag.is_empty() || tag.contains(|c: char| c == ',' || c.is_whitespace()) {
+                Err(format!("Invalid tag '{}'", tag))
+            } else {
+                Ok(tag.to_string())
+            }
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct BulkTagRequest {
+    /// The notes to update
+    #[serde(default)]
+    pub relpaths: Vec<String>,
+    /// Update every note under this folder (in addition to `relpaths`)
+    pub folder: Option<String>,
+    /// Tags to add
+    #[serde(default)]
+    pub add: Vec<String>,
+    /// Tags to remove
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct BulkTagResult {
+    pub relpath: String,
+    /// Whether the note was rewritten
+    pub changed: bool,
+    /// The note's tags after the change
+    pub tags: Vec<String>,
+    /// Why the note couldn't be updated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Add and remove tags on a set of notes (listed, under a folder, or both), then reindex
+/// the notes that changed. Notes that already have the right tags are left untouched.
+#[post("/notes/bulk-tag", data = "<req>")]
+pub async fn bulk_tag(
+    _write: WriteAccess,
+    vault: Vault,
+    req: JsonBody<BulkTagRequest>,
+) -> Result<Json<Vec<BulkTagResult>>, status::Custom<String>> {
+    let add = normalize_tags(&req.add).map_err(|e| status::Custom(Status::BadRequest, e))?;
+    let remove = normalize_tags(&req.remove).map_err(|e| status::Custom(Status::BadRequest, e))?;
+    if add.is_empty() && remove.is_empty() {
+        return Err(status::Custom(
+            Status::BadRequest,
+            "Nothing to add or remove".to_string(),
+        ));
+    }
+
+    let mut relpaths: Vec<String> = Vec::new();
+    for relpath in &req.relpaths {
+        if !relpaths.contains(relpath) {
+            relpaths.push(relpath.clone());
+        }
+    }
+    if let Some(folder) = &req.folder {
+        let prefix = match folder.trim_matches('/') {
+            "" => String::new(),
+            folder => format!("{}/", folder),
+        };
+        let notes = utils::list_note_relpaths(vault.name())
+            .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+        for relpath in notes {
+            if relpath.starts_with(&prefix) && !relpaths.contains(&relpath) {
+                relpaths.push(relpath);
+            }
+        }
+    }
+    if relpaths.is_empty() {
+        return Err(status::Custom(
+            Status::BadRequest,
+            "No notes given; pass relpaths or a folder".to_string(),
+        ));
+    }
+
+    let mut results = Vec::new();
+    for relpath in relpaths {
+        let path = match paths::resolve_in_vault(vault.name(), &relpath) {
+            Ok(path) => path,
+            Err(e) => {
+                results.push(BulkTagResult {
+                    relpath,
+                    changed: false,
+                    tags: Vec::new(),
+                    error: Some(e.1),
+                });
+                continue;
+            }
+        };
+        let Ok(raw) = fs::read_to_string(&path) else {
+            results.push(BulkTagResult {
+                relpath,
+                changed: false,
+                tags: Vec::new(),
+                error: Some("Note not found".to_string()),
+            });
+            continue;
+        };
+
+        let tags = note_tags(&frontmatter::parse_frontmatter(&raw).0);
+        let updated_tags = apply_tag_changes(&tags, &add, &remove);
+        if updated_tags == tags {
+            results.push(BulkTagResult {
+                relpath,
+                changed: false,
+                tags,
+                error: None,
+            });
+            continue;
+        }
+
+        let written = frontmatter::set_key(&raw, "tags", &Value::from(updated_tags.clone()))
+            .and_then(|updated| fs::write(&path, updated).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            results.push(BulkTagResult {
+                relpath,
+                changed: false,
+                tags,
+                error: Some(e),
+            });
+            continue;
+        }
+        if let Err(e) = search::reindex_note_async(vault.name(), &relpath)
+            .await
+            .map_err(|e| e.to_string())
+        {
+            eprintln!("Failed to reindex '{}': {}", relpath, e);
+        }
+        results.push(BulkTagResult {
+            relpath,
+            changed: true,
+            tags: updated_tags,
+            error: None,
+        });
+    }
+    Ok(Json(results))
+}
@@ -0,0 +1,171 @@
+use serde_json::Value;
+use std::error::Error;
+use std::path::Path;
+
+use crate::frontmatter;
+
+/// Where a note's title can come from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TitleSource {
+    /// The `title` field of the frontmatter
+    Frontmatter,
+    /// The first `# Heading` of the body
+    FirstH1,
+    /// The file name without its extension
+    Filename,
+}
+
+/// The title sources of content on its own, before it is saved anywhere
+pub const CONTENT_TITLE_ORDER: [TitleSource; 2] = [TitleSource::Frontmatter, TitleSource::FirstH1];
+
+/// Returns the order in which title sources are tried.
+///
+/// Configured with `NOTEMANCY_TITLE_ORDER`, a comma-separated list of `frontmatter`, `h1`
+/// and `filename`. Defaults to `frontmatter,filename`, matching notemancy-core's `get_title`.
+/// Unknown entries are ignored.
+pub fn title_order() -> Vec<TitleSource> {
+    let configured = std::env::var("NOTEMANCY_TITLE_ORDER").unwrap_or_default();
+    let order: Vec<TitleSource> = configured
+        .split(',')
+        .filter_map(|source| match source.trim().to_lowercase().as_str() {
+            "frontmatter" | "frontmatter_title" => Some(TitleSource::Frontmatter),
+            "h1" | "first_h1" => Some(TitleSource::FirstH1),
+            "filename" => Some(TitleSource::Filename),
+            _ => None,
+        })
+        .collect();
+    if order.is_empty() {
+        vec![TitleSource::Frontmatter, TitleSource::Filename]
+    } else {
+        order
+    }
+}
+
+/// Returns the text of the first level-one ATX heading, ignoring fenced code blocks.
+pub fn first_h1(body: &str) -> Option<String> {
+    let mut in_code_block = false;
+    for line in body.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        if let Some(heading) = trimmed.strip_prefix("# ") {
+            let heading = heading.trim().trim_end_matches('#').trim();
+            if !heading.is_empty() {
+                return Some(heading.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Returns the file name of a relpath without its extension.
+fn file_stem(relpath: &str) -> Option<String> {
+    Path::new(relpath)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+}
+
+/// Tries each title source in `order`, returning the first title found.
+///
+/// The `Filename` source is skipped when `relpath` is `None`, e.g. for content that
+/// hasn't been saved yet.
+pub fn find_title(
+    relpath: Option<&str>,
+    frontmatter: &Value,
+    body: &str,
+    order: &[TitleSource],
+) -> Option<String> {
+    order.iter().find_map(|source| match source {
+        TitleSource::Frontmatter => frontmatter
+            .get("title")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        TitleSource::FirstH1 => first_h1(body),
+        TitleSource::Filename => relpath.and_then(file_stem),
+    })
+}
+
+/// Resolves a note's title from its parsed contents, trying each source in `order`.
+///
+/// Falls back to the file name when no source yields a title.
+pub fn resolve_title(
+    relpath: &str,
+    frontmatter: &Value,
+    body: &str,
+    order: &[TitleSource],
+) -> String {
+    find_title(Some(relpath), frontmatter, body, order)
+        .or_else(|| file_stem(relpath))
+        .unwrap_or_default()
+}
+
+/// Reads a note and resolves its title with the configured `title_order`.
+pub fn note_title(vault_name: &str, relpath: &str) -> Result<String, Box<dyn Error>> {
+    let raw = notemancy_core::crud::read_note(vault_name, relpath, true)?;
+    let (frontmatter, body) = frontmatter::parse_frontmatter(&raw);
+    Ok(resolve_title(relpath, &frontmatter, &body, &title_order()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const H1_ONLY: &str = "# Weekly review\n\nNotes from the week.\n";
+
+    #[test]
+    fn note_with_only_an_h1_takes_its_title_from_it() {
+        let order = [
+            TitleSource::Frontmatter,
+            TitleSource::FirstH1,
+            TitleSource::Filename,
+        ];
+        assert_eq!(
+            resolve_title("journal/2024-w01.md", &Value::Null, H1_ONLY, &order),
+            "Weekly review"
+        );
+    }
+
+    #[test]
+    fn default_order_ignores_the_h1() {
+        let order = [TitleSource::Frontmatter, TitleSource::Filename];
+        assert_eq!(
+            resolve_title("journal/2024-w01.md", &Value::Null, H1_ONLY, &order),
+            "2024-w01"
+        );
+    }
+
+    #[test]
+    fn content_takes_its_title_from_the_frontmatter_then_the_h1() {
+        let frontmatter = serde_json::json!({ "title": "Planning" });
+        assert_eq!(
+            find_title(None, &frontmatter, H1_ONLY, &CONTENT_TITLE_ORDER),
+            Some("Planning".to_string())
+        );
+        assert_eq!(
+            find_title(None, &Value::Null, H1_ONLY, &CONTENT_TITLE_ORDER),
+            Some("Weekly review".to_string())
+        );
+        assert_eq!(
+            find_title(
+                None,
+                &Value::Null,
+                "No heading here.\n",
+                &CONTENT_TITLE_ORDER
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn headings_in_code_blocks_are_skipped() {
+        assert_eq!(
+            first_h1("```\n# not a title\n```\n# Title\n"),
+            Some("Title".to_string())
+        );
+    }
+}
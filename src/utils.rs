@@ -1,10 +1,13 @@
-use notemancy_core::config::get_vault_dir;
-use notemancy_core::utils as core_utils; // to use functions like get_title
+use chrono::{DateTime, Local};
 use rocket::serde::Serialize;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fs;
 use std::path::Path;
+use std::time::SystemTime;
+
+use crate::{frontmatter, paths, title};
 
 #[derive(Serialize)]
 #[serde(crate = "rocket::serde")]
@@ -17,23 +20,565 @@ pub struct TreeNode {
     pub title: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub children: Option<Vec<TreeNode>>,
+    /// Set on folders at the maximum tree depth, whose contents weren't listed
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// When a note was last modified (RFC3339); not set on folders
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    /// The latest modification time of the note, or of any note below the folder
+    #[serde(skip)]
+    pub latest: Option<SystemTime>,
+}
+
+/// How the entries of each folder are ordered by `build_file_tree`
+#[derive(Default, Clone, Copy, PartialEq)]
+pub enum TreeSort {
+    /// Folders first, then alphabetically
+    #[default]
+    Name,
+    /// Most recently modified first, folders by their most recently modified note
+    Modified,
+}
+
+/// Options controlling which entries `build_file_tree` includes.
+#[derive(Default)]
+pub struct TreeOptions {
+    /// Include the archive folder (hidden by default).
+    pub include_archived: bool,
+    pub sort: TreeSort,
+}
+
+/// Returns how many folder levels `build_file_tree` descends before giving up.
+///
+/// Configured with `NOTEMANCY_MAX_TREE_DEPTH`, defaulting to 32. This guards against
+/// runaway recursion (e.g. symlink loops) and is independent of any client-requested depth.
+pub fn max_tree_depth() -> usize {
+    std::env::var("NOTEMANCY_MAX_TREE_DEPTH")
+        .ok()
+        .and_then(|depth| depth.trim().parse().ok())
+        .filter(|&depth| depth > 0)
+        .unwrap_or(32)
+}
+
+/// Returns the vault-relative folder archived notes are moved into.
+///
+/// Configured with `NOTEMANCY_ARCHIVE_DIR`, defaulting to "archive".
+pub fn archive_dir() -> String {
+    std::env::var("NOTEMANCY_ARCHIVE_DIR")
+        .ok()
+        .map(|dir| dir.trim_matches('/').to_string())
+        .filter(|dir| !dir.is_empty())
+        .unwrap_or_else(|| "archive".to_string())
+}
+
+/// Name of the file at the vault root listing paths to leave out of the tree
+pub const IGNORE_FILE: &str = ".notemancyignore";
+
+/// Folder at the vault root where the server keeps its own files (such as note versions),
+/// always ignored
+pub const STATE_DIR: &str = ".notemancy";
+
+/// Patterns loaded from a vault's `.notemancyignore`.
+///
+/// One pattern per line; blank lines and lines starting with `#` are skipped. `*` matches
+/// any run of characters within a path component and `?` a single character. Patterns
+/// without a `/` match any file or folder with that name; patterns containing a `/` are
+/// matched against the vault-relative path, and ignoring a folder ignores everything in it.
+#[derive(Default)]
+pub struct IgnoreRules {
+    patterns: Vec<String>,
+}
+
+impl IgnoreRules {
+    /// Loads the ignore file from the vault root. A missing file ignores nothing but the
+    /// `STATE_DIR`.
+    pub fn load(root: &Path) -> IgnoreRules {
+        let mut patterns: Vec<String> = fs::read_to_string(root.join(IGNORE_FILE))
+            .map(|content| {
+                content
+                    .lines()
+                    .map(|line| line.trim())
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(|line| line.trim_matches('/').to_string())
+                    .filter(|line| !line.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        patterns.push(STATE_DIR.to_string());
+        IgnoreRules { patterns }
+    }
+
+    /// Whether the vault-relative path (using `/` separators) is ignored.
+    pub fn is_ignored(&self, relpath: &str) -> bool {
+        let components: Vec<&str> = relpath.split('/').collect();
+        self.patterns.iter().any(|pattern| {
+            if pattern.contains('/') {
+                // Match the full path or any of its parent folders.
+                (1..=components.len()).any(|n| glob_match(pattern, &components[..n].join("/")))
+            } else {
+                components
+                    .iter()
+                    .any(|component| glob_match(pattern, component))
+            }
+        })
+    }
+}
+
+/// Matches text against a glob pattern where `*` never crosses a `/`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    fn matches(pattern: &[char], text: &[char]) -> bool {
+        match pattern.split_first() {
+            None => text.is_empty(),
+            Some(('*', rest)) => (0..=text.len())
+                .take_while(|&i| i == 0 || text[i - 1] != '/')
+                .any(|i| matches(rest, &text[i..])),
+            Some(('?', rest)) => {
+                matches!(text.first(), Some(c) if *c != '/') && matches(rest, &text[1..])
+            }
+            Some((c, rest)) => text.first() == Some(c) && matches(rest, &text[1..]),
+        }
+    }
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    matches(&pattern, &text)
+}
+
+/// Returns the file extensions recognised as notes, lowercased and without the leading dot.
+///
+/// Configured with a comma-separated `NOTEMANCY_NOTE_EXTENSIONS`, defaulting to "md,markdown".
+pub fn note_extensions() -> Vec<String> {
+    parse_note_extensions(&std::env::var("NOTEMANCY_NOTE_EXTENSIONS").unwrap_or_default())
+}
+
+/// Parses a comma-separated list of note extensions, falling back to the defaults when it
+/// names none.
+fn parse_note_extensions(configured: &str) -> Vec<String> {
+    let configured: Vec<String> = configured
+        .split(',')
+        .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect();
+    if configured.is_empty() {
+        vec!["md".to_string(), "markdown".to_string()]
+    } else {
+        configured
+    }
+}
+
+/// Checks that a relpath names a note file, returning a message suitable for a 400 response.
+pub fn validate_note_extension(relpath: &str) -> Result<(), String> {
+    if paths::is_note_file(Path::new(relpath)) {
+        Ok(())
+    } else {
+        Err(format!(
+            "'{}' doesn't have a note extension ({})",
+            relpath,
+            note_extensions().join(", ")
+        ))
+    }
+}
+
+/// Returns `relpath` if nothing exists there in `root`, otherwise the first free variant
+/// with `-2`, `-3`, ... appended to the file stem (`notes/idea.md` -> `notes/idea-2.md`).
+pub fn unique_relpath(root: &Path, relpath: &str) -> String {
+    if !root.join(relpath).exists() {
+        return relpath.to_string();
+    }
+    let (folder, file_name) = match relpath.rsplit_once('/') {
+        Some((folder, file_name)) => (format!("{}/", folder), file_name),
+        None => (String::new(), relpath),
+    };
+    let (stem, extension) = match file_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (file_name, String::new()),
+    };
+    (2..)
+        .map(|n| format!("{}{}-{}{}", folder, stem, n, extension))
+        .find(|candidate| !root.join(candidate).exists())
+        .unwrap()
+}
+
+/// Whether relpaths from clients are matched to files case-insensitively, set with
+/// `NOTEMANCY_CASE_INSENSITIVE_PATHS=1`. Useful on macOS and Windows, where `Notes/X.md`
+/// and `notes/x.md` name the same file.
+pub fn case_insensitive_paths() -> bool {
+    std::env::var("NOTEMANCY_CASE_INSENSITIVE_PATHS")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Rewrites a relpath to the case used on disk, so a note gets one relpath (and one search
+/// document) whatever case a client uses.
+///
+/// Each component is matched case-insensitively against the directory entries when an exact
+/// match doesn't exist. Components that match nothing (e.g. a note about to be created) are
+/// kept as given. Returns the relpath unchanged unless `case_insensitive_paths` is set.
+pub fn canonical_relpath(root: &Path, relpath: &str) -> String {
+    if case_insensitive_paths() {
+        on_disk_relpath(root, relpath)
+    } else {
+        relpath.to_string()
+    }
+}
+
+/// Matches each component of a relpath to the case of the entry on disk, as described in
+/// `canonical_relpath`.
+fn on_disk_relpath(root: &Path, relpath: &str) -> String {
+    let mut dir = root.to_path_buf();
+    let mut components = Vec::new();
+    for component in relpath.split('/').filter(|c| !c.is_empty()) {
+        let names: Vec<String> = fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let lowered = component.to_lowercase();
+        let on_disk = names
+            .iter()
+            .find(|name| *name == component)
+            .or_else(|| names.iter().find(|name| name.to_lowercase() == lowered))
+            .cloned();
+        let component = on_disk.unwrap_or_else(|| component.to_string());
+        dir.push(&component);
+        components.push(component);
+    }
+    components.join("/")
+}
+
+/// Lists the relative paths of every note in the vault, skipping ignored paths.
+pub fn list_note_relpaths(vault_name: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    list_relpaths(vault_name, true)
+}
+
+/// Lists the relative paths of every file in the vault (notes and attachments alike),
+/// skipping ignored paths.
+pub fn list_file_relpaths(vault_name: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    list_relpaths(vault_name, false)
+}
+
+fn list_relpaths(vault_name: &str, notes_only: bool) -> Result<Vec<String>, Box<dyn Error>> {
+    let vault_dir = paths::vault_root(vault_name)?;
+    let root = vault_dir.as_path();
+    let ignore = IgnoreRules::load(root);
+    let mut relpaths = Vec::new();
+    collect_relpaths(root, root, &ignore, notes_only, 1, &mut relpaths)?;
+    relpaths.sort();
+    Ok(relpaths)
+}
+
+/// Lists the relative paths of the notes below `dir`, a folder inside the vault at `root`,
+/// skipping ignored paths.
+pub fn note_relpaths_below(root: &Path, dir: &Path) -> Result<Vec<String>, Box<dyn Error>> {
+    let ignore = IgnoreRules::load(root);
+    let mut relpaths = Vec::new();
+    let depth = paths::relpath_of(root, dir)
+        .split('/')
+        .filter(|component| !component.is_empty())
+        .count();
+    collect_relpaths(dir, root, &ignore, true, depth + 1, &mut relpaths)?;
+    relpaths.sort();
+    Ok(relpaths)
+}
+
+/// Recursively collects the relative paths of files below `dir` into `relpaths`, only
+/// keeping notes when `notes_only` is set.
+///
+/// `depth` is the nesting level of `dir`'s entries; folders at `max_tree_depth` are not
+/// descended into. Symlinked folders are never followed, so a symlink loop can't trap the
+/// walk, and symlinked files are only kept when they point inside the vault.
+fn collect_relpaths(
+    dir: &Path,
+    root: &Path,
+    ignore: &IgnoreRules,
+    notes_only: bool,
+    depth: usize,
+    relpaths: &mut Vec<String>,
+) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let relpath = paths::relpath_of(root, &path);
+        if ignore.is_ignored(&relpath) {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if depth >= max_tree_depth() {
+                eprintln!(
+                    "Warning: '{}' is nested deeper than {} levels; its contents are not listed",
+                    relpath,
+                    max_tree_depth()
+                );
+                continue;
+            }
+            collect_relpaths(&path, root, ignore, notes_only, depth + 1, relpaths)?;
+            continue;
+        }
+        let is_file = if file_type.is_symlink() {
+            path.is_file() && paths::check_within(root, &path).is_ok()
+        } else {
+            file_type.is_file()
+        };
+        if is_file && (!notes_only || paths::is_note_file(&path)) {
+            relpaths.push(relpath);
+        }
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct FileSize {
+    pub relpath: String,
+    pub bytes: u64,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct DiskUsage {
+    pub total_bytes: u64,
+    /// Bytes per lowercased file extension ("" for files without one)
+    pub by_extension: BTreeMap<String, u64>,
+    /// The largest files, biggest first
+    pub largest: Vec<FileSize>,
+}
+
+/// Sums the size of every file in the vault, by extension, and finds the `largest` biggest.
+pub fn disk_usage(vault_name: &str, largest: usize) -> Result<DiskUsage, Box<dyn Error>> {
+    let vault_dir = paths::vault_root(vault_name)?;
+    let mut usage = DiskUsage {
+        total_bytes: 0,
+        by_extension: BTreeMap::new(),
+        largest: Vec::new(),
+    };
+    let mut files = Vec::new();
+    for relpath in list_file_relpaths(vault_name)? {
+        let bytes = fs::metadata(vault_dir.join(&relpath))?.len();
+        let extension = Path::new(&relpath)
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        usage.total_bytes += bytes;
+        *usage.by_extension.entry(extension).or_default() += bytes;
+        files.push(FileSize { relpath, bytes });
+    }
+    files.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.relpath.cmp(&b.relpath)));
+    files.truncate(largest);
+    usage.largest = files;
+    Ok(usage)
+}
+
+/// A note read from disk, with its frontmatter parsed
+pub struct NoteRecord {
+    pub relpath: String,
+    pub title: String,
+    /// The parsed frontmatter as a JSON object (empty when absent)
+    pub frontmatter: serde_json::Value,
+    /// The note content without the frontmatter
+    pub body: String,
+    /// The file's last modification time
+    pub modified: Option<SystemTime>,
+}
+
+/// Reads every note in the vault (see `list_note_relpaths`) and parses its frontmatter.
+pub fn load_notes(vault_name: &str) -> Result<Vec<NoteRecord>, Box<dyn Error>> {
+    let vault_dir = paths::vault_root(vault_name)?;
+    let mut notes = Vec::new();
+    for relpath in list_note_relpaths(vault_name)? {
+        let raw = notemancy_core::crud::read_note(vault_name, &relpath, true)?;
+        let (frontmatter, body) = frontmatter::parse_frontmatter(&raw);
+        let title = title::resolve_title(&relpath, &frontmatter, &body, &title::title_order());
+        let modified = fs::metadata(vault_dir.join(&relpath))
+            .and_then(|m| m.modified())
+            .ok();
+        notes.push(NoteRecord {
+            relpath,
+            title,
+            frontmatter,
+            body,
+            modified,
+        });
+    }
+    Ok(notes)
+}
+
+#[derive(Serialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct DuplicateGroup {
+    /// Hash of the shared (whitespace-normalized) body
+    pub hash: String,
+    pub notes: Vec<String>,
+}
+
+/// Groups notes whose bodies are identical once whitespace is normalized, largest groups
+/// first. Frontmatter is ignored and notes with empty bodies are skipped.
+pub fn duplicate_groups(notes: &[NoteRecord]) -> Vec<DuplicateGroup> {
+    let mut by_hash: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for note in notes {
+        let normalized = note.body.split_whitespace().collect::<Vec<_>>().join(" ");
+        if normalized.is_empty() {
+            continue;
+        }
+        by_hash
+            .entry(crate::search::content_hash(&normalized))
+            .or_default()
+            .push(note.relpath.clone());
+    }
+
+    let mut groups: Vec<DuplicateGroup> = by_hash
+        .into_iter()
+        .filter(|(_, notes)| notes.len() > 1)
+        .map(|(hash, notes)| DuplicateGroup { hash, notes })
+        .collect();
+    groups.sort_by(|a, b| {
+        b.notes
+            .len()
+            .cmp(&a.notes.len())
+            .then(a.notes.cmp(&b.notes))
+    });
+    groups
+}
+
+#[derive(Serialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct FrontmatterIssue {
+    pub relpath: String,
+    pub key: String,
+    /// The type of the value in this note (see `frontmatter::value_type`)
+    pub found_type: String,
+    /// The type most notes use for the key
+    pub expected_type: String,
+}
+
+/// Finds frontmatter values whose type differs from the one most notes use for the same key,
+/// ordered by key then relpath.
+///
+/// Null values are ignored. Keys set in fewer than two notes, or without a single most
+/// common type, are skipped.
+pub fn frontmatter_issues(notes: &[NoteRecord]) -> Vec<FrontmatterIssue> {
+    let mut types: BTreeMap<&str, BTreeMap<&'static str, usize>> = BTreeMap::new();
+    for note in notes {
+        let Some(fields) = note.frontmatter.as_object() else {
+            continue;
+        };
+        for (key, value) in fields {
+            if !value.is_null() {
+                *types
+                    .entry(key)
+                    .or_default()
+                    .entry(frontmatter::value_type(value))
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    let expected: BTreeMap<&str, &'static str> = types
+        .into_iter()
+        .filter_map(|(key, counts)| {
+            let max = *counts.values().max()?;
+            let mut dominant = counts.iter().filter(|(_, count)| **count == max);
+            let (found, _) = dominant.next()?;
+            let total: usize = counts.values().sum();
+            (total > 1 && dominant.next().is_none()).then_some((key, *found))
+        })
+        .collect();
+
+    let mut issues = Vec::new();
+    for note in notes {
+        let Some(fields) = note.frontmatter.as_object() else {
+            continue;
+        };
+        for (key, value) in fields {
+            let found = frontmatter::value_type(value);
+            if let Some(expected) = expected.get(key.as_str())
+                && !value.is_null()
+                && found != *expected
+            {
+                issues.push(FrontmatterIssue {
+                    relpath: note.relpath.clone(),
+                    key: key.clone(),
+                    found_type: found.to_string(),
+                    expected_type: expected.to_string(),
+                });
+            }
+        }
+    }
+    issues.sort_by(|a, b| a.key.cmp(&b.key).then_with(|| a.relpath.cmp(&b.relpath)));
+    issues
+}
+
+/// Renders a frontmatter value as a CSV cell: arrays are joined with `;`, missing values
+/// and nulls are empty, and nested objects are written as JSON.
+pub fn csv_cell(value: Option<&serde_json::Value>) -> String {
+    match value {
+        None | Some(serde_json::Value::Null) => String::new(),
+        Some(serde_json::Value::String(text)) => text.clone(),
+        Some(serde_json::Value::Array(items)) => items
+            .iter()
+            .map(|item| csv_cell(Some(item)))
+            .collect::<Vec<_>>()
+            .join(";"),
+        Some(other) => other.to_string(),
+    }
 }
 
-/// Builds the file tree for the vault folder of the "main" vault,
+/// Joins cells into a CSV line (RFC 4180), quoting cells that contain commas, quotes or
+/// line breaks.
+pub fn csv_row(cells: &[String]) -> String {
+    let escaped: Vec<String> = cells
+        .iter()
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.clone()
+            }
+        })
+        .collect();
+    format!("{}\r\n", escaped.join(","))
+}
+
+/// Name of the hidden file that keeps a folder without notes in the tree
+pub const KEEP_FILE: &str = ".keep";
+
+/// Removes the folders a deleted file at `relpath` left empty, from its own folder up to
+/// (not including) the vault root. Folders holding anything else, such as a `KEEP_FILE`,
+/// stay.
+pub fn prune_empty_folders(root: &Path, relpath: &str) {
+    let mut folder = Path::new(relpath).parent();
+    while let Some(dir) = folder.filter(|dir| !dir.as_os_str().is_empty()) {
+        if fs::remove_dir(root.join(dir)).is_err() {
+            break;
+        }
+        folder = dir.parent();
+    }
+}
+
+/// Builds the file tree for the folder of the given vault,
 /// skipping the root level. Returns a vector of TreeNode representing the top-level items.
-pub fn build_file_tree() -> Result<Vec<TreeNode>, Box<dyn Error>> {
-    let vault_dir = get_vault_dir("main")?;
-    let root_path = Path::new(&vault_dir);
+pub fn build_file_tree(
+    vault_name: &str,
+    options: &TreeOptions,
+) -> Result<Vec<TreeNode>, Box<dyn Error>> {
+    let vault_dir = paths::vault_root(vault_name)?;
+    let root_path = vault_dir.as_path();
+    let ignore = IgnoreRules::load(root_path);
     let mut nodes = Vec::new();
 
     // Instead of including the root, iterate its children.
     for entry in fs::read_dir(root_path)? {
         let entry = entry?;
-        if let Some(child_node) = build_tree_node(&entry.path(), root_path, "main")? {
+        if let Some(child_node) =
+            build_tree_node(&entry.path(), root_path, vault_name, options, &ignore, 1)?
+        {
             nodes.push(child_node);
         }
     }
-    sort_nodes(&mut nodes);
+    sort_nodes(&mut nodes, options.sort);
     if nodes.is_empty() {
         Err("Vault directory is empty".into())
     } else {
@@ -43,14 +588,27 @@ pub fn build_file_tree() -> Result<Vec<TreeNode>, Box<dyn Error>> {
 
 /// Recursively builds a tree node for the given path.
 /// - `root` is the vault directory used to compute relative paths.
-/// - `vault_name` is passed to core_utils::get_title for markdown files.
-/// Files that are not markdown (neither .md nor .markdown) are skipped.
-/// Directories that do not contain any markdown files are skipped as well.
+/// - `vault_name` is used to read notes for their titles (see `title::note_title`).
+/// - `options` decides whether the archive folder is included.
+/// - `ignore` holds the vault's `.notemancyignore` patterns.
+/// - `depth` is the nesting level of `path`; folders at `max_tree_depth` are returned
+///   empty and marked `truncated` instead of being descended into.
+/// Files without a note extension (see `note_extensions`) are skipped.
+/// Directories that do not contain any notes are skipped as well, unless they hold a
+/// `KEEP_FILE`.
 fn build_tree_node(
     path: &Path,
     root: &Path,
     vault_name: &str,
+    options: &TreeOptions,
+    ignore: &IgnoreRules,
+    depth: usize,
 ) -> Result<Option<TreeNode>, Box<dyn Error>> {
+    let relpath = paths::relpath_of(root, path);
+    if ignore.is_ignored(&relpath) {
+        return Ok(None);
+    }
+
     // Use the file name if available; otherwise (for the root) use the full path.
     let name = if let Some(file_name) = path.file_name() {
         file_name.to_string_lossy().to_string()
@@ -60,62 +618,391 @@ fn build_tree_node(
 
     let metadata = fs::metadata(path)?;
     if metadata.is_dir() {
+        // Archived notes stay on disk (and searchable) but are hidden from the tree by default.
+        if !options.include_archived && path == root.join(archive_dir()) {
+            return Ok(None);
+        }
+        if depth >= max_tree_depth() {
+            eprintln!(
+                "Warning: '{}' is nested deeper than {} levels; its contents are not listed",
+                relpath,
+                max_tree_depth()
+            );
+            return Ok(Some(TreeNode {
+                name,
+                is_dir: true,
+                relpath: None,
+                title: None,
+                children: Some(Vec::new()),
+                truncated: true,
+                last_modified: None,
+                latest: None,
+            }));
+        }
         let mut children = Vec::new();
         for entry in fs::read_dir(path)? {
             let entry = entry?;
-            if let Some(child_node) = build_tree_node(&entry.path(), root, vault_name)? {
+            if let Some(child_node) =
+                build_tree_node(&entry.path(), root, vault_name, options, ignore, depth + 1)?
+            {
                 children.push(child_node);
             }
         }
         // If no children remain (i.e. no markdown files in this folder/subfolders), skip it.
-        if children.is_empty() {
+        if children.is_empty() && !path.join(KEEP_FILE).is_file() {
             return Ok(None);
         }
-        sort_nodes(&mut children);
+        sort_nodes(&mut children, options.sort);
+        let latest = children.iter().filter_map(|child| child.latest).max();
         Ok(Some(TreeNode {
             name,
             is_dir: true,
             relpath: None,
             title: None,
             children: Some(children),
+            truncated: false,
+            last_modified: None,
+            latest,
         }))
     } else {
-        // Check file extension (in lowercase) to see if it's markdown.
-        let extension = path
-            .extension()
-            .and_then(|s| s.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-        if extension == "md" || extension == "markdown" {
-            // Compute the relative path from the vault root.
-            let relpath = path.strip_prefix(root)?.to_string_lossy().to_string();
-            // Get the note title using the notemancy-core function.
-            let title =
-                core_utils::get_title(vault_name, &relpath).unwrap_or_else(|_| "".to_string());
+        // Check the file extension against the configured note extensions.
+        if paths::is_note_file(path) {
+            let title = title::note_title(vault_name, &relpath).unwrap_or_default();
+            let latest = metadata.modified().ok();
             Ok(Some(TreeNode {
                 name,
                 is_dir: false,
                 relpath: Some(relpath),
                 title: Some(title),
                 children: None,
+                truncated: false,
+                last_modified: latest
+                    .map(|modified| DateTime::<Local>::from(modified).to_rfc3339()),
+                latest,
             }))
         } else {
-            // Ignore files that are not markdown.
+            // Ignore files that are not notes.
             Ok(None)
         }
     }
 }
 
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct FolderNode {
+    pub name: String,
+    /// The folder path relative to the vault root
+    pub path: String,
+    pub children: Vec<FolderNode>,
+}
+
+/// Extracts the folder hierarchy from a built file tree, dropping note entries.
+/// - `parent` is the vault-relative path of the folder containing `nodes` ("" for the root).
+pub fn folder_tree(nodes: &[TreeNode], parent: &str) -> Vec<FolderNode> {
+    nodes
+        .iter()
+        .filter(|node| node.is_dir)
+        .map(|node| {
+            let path = if parent.is_empty() {
+                node.name.clone()
+            } else {
+                format!("{}/{}", parent, node.name)
+            };
+            let children = folder_tree(node.children.as_deref().unwrap_or(&[]), &path);
+            FolderNode {
+                name: node.name.clone(),
+                path,
+                children,
+            }
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct TextMatch {
+    /// 1-based line number
+    pub line: usize,
+    /// 1-based column, counted in characters
+    pub column: usize,
+    /// The text surrounding the match on the same line
+    pub context: String,
+}
+
+/// Number of characters of context kept on either side of a match
+const MATCH_CONTEXT_CHARS: usize = 40;
+
+/// Lowercases text one character at a time so character offsets stay aligned with the input.
+fn lowercase_chars(text: &str) -> Vec<char> {
+    text.chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect()
+}
+
+/// Finds every case-insensitive occurrence of the query's whitespace-separated terms in
+/// the text, ordered by position. Offsets are counted in characters, never bytes.
+pub fn find_matches(text: &str, query: &str) -> Vec<TextMatch> {
+    let terms: Vec<Vec<char>> = query.split_whitespace().map(lowercase_chars).collect();
+    let mut matches = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let original: Vec<char> = line.chars().collect();
+        let lowered = lowercase_chars(line);
+        for term in &terms {
+            let mut start = 0;
+            while start + term.len() <= lowered.len() {
+                if lowered[start..start + term.len()] != term[..] {
+                    start += 1;
+                    continue;
+                }
+                let from = start.saturating_sub(MATCH_CONTEXT_CHARS);
+                let to = (start + term.len() + MATCH_CONTEXT_CHARS).min(original.len());
+                matches.push(TextMatch {
+                    line: index + 1,
+                    column: start + 1,
+                    context: original[from..to]
+                        .iter()
+                        .collect::<String>()
+                        .trim()
+                        .to_string(),
+                });
+                start += term.len();
+            }
+        }
+    }
+
+    matches.sort_by_key(|m| (m.line, m.column));
+    matches
+}
+
+/// Strips inline markdown from a line: emphasis markers, code ticks, images, and link
+/// syntax (keeping the link text or wikilink alias).
+pub fn plain_text(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut rest = line;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("![")
+            && let Some(end) = after.find(')')
+        {
+            // Drop images entirely.
+            rest = &after[end + 1..];
+        } else if let Some(after) = rest.strip_prefix("[[")
+            && let Some(end) = after.find("]]")
+        {
+            let inner = &after[..end];
+            text.push_str(inner.rsplit('|').next().unwrap_or(inner));
+            rest = &after[end + 2..];
+        } else if let Some(after) = rest.strip_prefix('[')
+            && let Some(close) = after.find("](")
+            && let Some(end) = after[close..].find(')')
+        {
+            text.push_str(&after[..close]);
+            rest = &after[close + end + 1..];
+        } else {
+            let c = rest.chars().next().unwrap_or_default();
+            if !matches!(c, '*' | '_' | '`' | '~') {
+                text.push(c);
+            }
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    text
+}
+
+/// Counts the words of a note body: whitespace-separated tokens containing at least one
+/// letter or digit, so list markers and heading hashes aren't counted.
+pub fn word_count(body: &str) -> usize {
+    body.split_whitespace()
+        .filter(|token| token.chars().any(char::is_alphanumeric))
+        .count()
+}
+
+/// Builds a short plain-text excerpt from the first paragraph of a note body.
+///
+/// Headings, fenced code blocks and horizontal rules are skipped. The excerpt is cut at a
+/// word boundary (never inside a character) and ends with "…" when truncated.
+pub fn excerpt(body: &str, max_chars: usize) -> String {
+    let mut paragraph: Vec<String> = Vec::new();
+    let mut in_code_block = false;
+    for line in body.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block || trimmed.starts_with('#') || trimmed == "---" || trimmed == "***" {
+            continue;
+        }
+        if trimmed.is_empty() {
+            if paragraph.is_empty() {
+                continue;
+            }
+            break;
+        }
+        let trimmed = trimmed.trim_start_matches(['>', '-', '+', ' ']);
+        paragraph.push(plain_text(trimmed));
+    }
+
+    let text = paragraph.join(" ");
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let truncated: String = text.chars().take(max_chars).collect();
+    let cut = truncated
+        .rfind(char::is_whitespace)
+        .map(|i| &truncated[..i])
+        .unwrap_or(&truncated);
+    format!("{}…", cut.trim_end())
+}
+
+/// Returns the longest prefix of `text` that fits in `max_bytes` without splitting a
+/// character.
+pub fn truncate_bytes(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// File signatures of common binary formats, as they appear when the bytes are decoded as text
+const BINARY_SIGNATURES: &[(&str, &str)] = &[
+    ("\u{89}PNG", "PNG image"),
+    ("\u{FF}\u{D8}\u{FF}", "JPEG image"),
+    ("GIF87a", "GIF image"),
+    ("GIF89a", "GIF image"),
+    ("%PDF-", "PDF document"),
+    ("PK\u{3}\u{4}", "ZIP archive"),
+    ("\u{7F}ELF", "executable"),
+];
+
+/// Checks that note content is plain text before it is written to the vault.
+///
+/// Rejects content containing NUL bytes, content starting with a well-known binary file
+/// signature, and content made up largely of control characters. Returns a message
+/// suitable for a 400 response.
+pub fn validate_text_content(content: &str) -> Result<(), String> {
+    if content.contains('\0') {
+        return Err("Note content contains NUL bytes and looks like binary data".to_string());
+    }
+    if let Some((_, kind)) = BINARY_SIGNATURES
+        .iter()
+        .find(|(signature, _)| content.starts_with(signature))
+    {
+        return Err(format!("Note content looks like a {}, not text", kind));
+    }
+
+    // Text may contain tabs, newlines and form feeds but rarely any other control characters.
+    let sample: Vec<char> = content.chars().take(8192).collect();
+    let control = sample
+        .iter()
+        .filter(|c| c.is_control() && !matches!(c, '\t' | '\n' | '\r' | '\u{c}'))
+        .count();
+    if !sample.is_empty() && control * 10 > sample.len() {
+        return Err("Note content contains too many control characters to be text".to_string());
+    }
+
+    Ok(())
+}
+
 /// Recursively sorts nodes so that directories come first and items are ordered alphabetically (case‑insensitive).
-fn sort_nodes(nodes: &mut Vec<TreeNode>) {
-    nodes.sort_by(|a, b| match (a.is_dir, b.is_dir) {
-        (true, false) => Ordering::Less,
-        (false, true) => Ordering::Greater,
-        _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+fn sort_nodes(nodes: &mut Vec<TreeNode>, sort: TreeSort) {
+    let by_name = |a: &TreeNode, b: &TreeNode| a.name.to_lowercase().cmp(&b.name.to_lowercase());
+    nodes.sort_by(|a, b| match sort {
+        TreeSort::Name => match (a.is_dir, b.is_dir) {
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            _ => by_name(a, b),
+        },
+        TreeSort::Modified => b.latest.cmp(&a.latest).then_with(|| by_name(a, b)),
     });
     for node in nodes.iter_mut() {
         if let Some(ref mut children) = node.children {
-            sort_nodes(children);
+            sort_nodes(children, sort);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configured_extensions_are_lowercased_without_dots() {
+        assert_eq!(
+            parse_note_extensions("md, .MDX ,markdown"),
+            vec!["md", "mdx", "markdown"]
+        );
+        assert_eq!(parse_note_extensions(""), vec!["md", "markdown"]);
+        assert_eq!(parse_note_extensions(" , "), vec!["md", "markdown"]);
+    }
+
+    fn walk(root: &Path) -> Vec<String> {
+        let mut relpaths = Vec::new();
+        collect_relpaths(root, root, &IgnoreRules::load(root), true, 1, &mut relpaths).unwrap();
+        relpaths.sort();
+        relpaths
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_folders_are_not_followed() {
+        let vault = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let root = vault.path();
+        fs::create_dir(root.join("projects")).unwrap();
+        fs::write(root.join("projects/plan.md"), "").unwrap();
+        fs::write(outside.path().join("secret.md"), "").unwrap();
+        std::os::unix::fs::symlink(root, root.join("projects/loop")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), root.join("linked")).unwrap();
+
+        assert_eq!(walk(root), vec!["projects/plan.md"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_notes_are_kept_only_inside_the_vault() {
+        let vault = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let root = vault.path();
+        fs::write(root.join("plan.md"), "").unwrap();
+        fs::write(outside.path().join("secret.md"), "").unwrap();
+        std::os::unix::fs::symlink(root.join("plan.md"), root.join("alias.md")).unwrap();
+        std::os::unix::fs::symlink(outside.path().join("secret.md"), root.join("secret.md"))
+            .unwrap();
+
+        assert_eq!(walk(root), vec!["alias.md", "plan.md"]);
+    }
+
+    #[test]
+    fn folders_past_the_depth_cap_are_not_listed() {
+        let vault = tempfile::tempdir().unwrap();
+        let root = vault.path();
+        let mut dir = root.to_path_buf();
+        for level in 1..=max_tree_depth() + 1 {
+            dir.push(format!("level{}", level));
         }
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("deep.md"), "").unwrap();
+        fs::write(root.join("level1/shallow.md"), "").unwrap();
+
+        assert_eq!(walk(root), vec!["level1/shallow.md"]);
+    }
+
+    #[test]
+    fn mismatched_case_resolves_to_the_on_disk_relpath() {
+        let vault = tempfile::tempdir().unwrap();
+        let root = vault.path();
+        fs::create_dir(root.join("Notes")).unwrap();
+        fs::write(root.join("Notes/X.md"), "x").unwrap();
+
+        // Both spellings get the id the note was indexed under.
+        assert_eq!(on_disk_relpath(root, "notes/x.md"), "Notes/X.md");
+        assert_eq!(on_disk_relpath(root, "Notes/X.md"), "Notes/X.md");
+        // A note that doesn't exist yet keeps its own name in an existing folder.
+        assert_eq!(on_disk_relpath(root, "NOTES/new.md"), "Notes/new.md");
     }
 }
@@ -0,0 +1,137 @@
+use rocket::Data;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::uri::Origin;
+use rocket::http::{RawStr, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+
+/// Header clients can use to pick a vault instead of appending `?vault=` to every request
+pub const VAULT_HEADER: &str = "X-Notemancy-Vault";
+
+/// The vault a request operates on.
+///
+/// Resolved once per request with the precedence: a `/vaults/<vault>/...` path prefix (see
+/// `VaultPrefix`), then the `?vault=` query parameter, then the `X-Notemancy-Vault` header,
+/// then the configured default vault. Requests naming a vault that isn't configured are
+/// rejected with 404 when it came from the path and 400 otherwise.
+pub struct Vault(pub String);
+
+impl Vault {
+    /// The vault name as configured in notemancy-core
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Returns the default vault name.
+///
+/// Read from `default_vault.txt` in `NOTEMANCY_CONF_DIR`, falling back to "main" when the
+/// file (or the variable) doesn't exist.
+pub fn default_vault_name() -> String {
+    std::env::var("NOTEMANCY_CONF_DIR")
+        .ok()
+        .and_then(|dir| {
+            std::fs::read_to_string(std::path::Path::new(&dir).join("default_vault.txt")).ok()
+        })
+        .map(|content| content.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "main".to_string())
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Vault {
+    type Error = String;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        if let PathVault(Some(name)) = req.local_cache(|| PathVault(None)) {
+            return match notemancy_core::config::get_vault_dir(name) {
+                Ok(_) => Outcome::Success(Vault(name.clone())),
+                Err(_) => Outcome::Error((Status::NotFound, not_found_message(name))),
+            };
+        }
+
+        let from_query = match req.query_value::<String>("vault") {
+            Some(Ok(name)) if !name.trim().is_empty() => Some(name.trim().to_string()),
+            _ => None,
+        };
+        let from_header = req
+            .headers()
+            .get_one(VAULT_HEADER)
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty());
+        let name = from_query
+            .or(from_header)
+            .unwrap_or_else(default_vault_name);
+
+        match notemancy_core::config::get_vault_dir(&name) {
+            Ok(_) => Outcome::Success(Vault(name)),
+            Err(e) => {
+                let message = format!("Vault '{}' is not configured: {}", name, e);
+                req.local_cache(|| RejectedVault(Some(message.clone())));
+                Outcome::Error((Status::BadRequest, message))
+            }
+        }
+    }
+}
+
+/// The vault named by the request path, kept by `VaultPrefix` for the guard
+struct PathVault(Option<String>);
+
+/// Why the guard rejected the vault named by the query or header, kept for the 400 catcher
+struct RejectedVault(Option<String>);
+
+/// Why the `Vault` guard rejected a request with 400, if it did.
+pub fn rejection(req: &Request<'_>) -> Option<String> {
+    req.local_cache(|| RejectedVault(None)).0.clone()
+}
+
+fn not_found_message(name: &str) -> String {
+    format!("Vault '{}' does not exist", name)
+}
+
+/// Fairing that serves every route under `/vaults/<vault>/` as well, so
+/// `GET /vaults/work/notes/tree` is `GET /notes/tree` on the "work" vault. The prefix is
+/// stripped before routing and the vault handed to the `Vault` guard.
+pub struct VaultPrefix;
+
+#[rocket::async_trait]
+impl Fairing for VaultPrefix {
+    fn info(&self) -> Info {
+        Info {
+            name: "Route /vaults/<vault>/ paths to the named vault",
+            kind: Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, _: &mut Data<'_>) {
+        let path = req.uri().path().as_str();
+        let Some((raw_name, rest)) = path
+            .strip_prefix("/vaults/")
+            .and_then(|path| path.split_once('/'))
+        else {
+            return;
+        };
+        let name = RawStr::new(raw_name).percent_decode_lossy().to_string();
+        if name.trim().is_empty() {
+            return;
+        }
+        let uri = match req.uri().query() {
+            Some(query) => format!("/{}?{}", rest, query),
+            None => format!("/{}", rest),
+        };
+        if let Ok(uri) = Origin::parse_owned(uri) {
+            req.local_cache(|| PathVault(Some(name)));
+            req.set_uri(uri);
+        }
+    }
+}
+
+/// Names the missing vault when a `/vaults/<vault>/` request fails with 404.
+#[catch(404)]
+pub fn not_found(req: &Request<'_>) -> String {
+    match req.local_cache(|| PathVault(None)) {
+        PathVault(Some(name)) if notemancy_core::config::get_vault_dir(name).is_err() => {
+            not_found_message(name)
+        }
+        _ => "Not Found".to_string(),
+    }
+}
@@ -0,0 +1,151 @@
+use chrono::{SecondsFormat, Utc};
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::serde::{Deserialize, json::Json};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::access::WriteAccess;
+use crate::errors::JsonBody;
+use crate::vault::Vault;
+use crate::{paths, search, utils};
+
+/// Folder below the vault's `STATE_DIR` holding previous versions of notes
+const HISTORY_DIR: &str = "history";
+
+/// Number of previous versions kept per note, from `NOTEMANCY_NOTE_VERSIONS` (default 10)
+pub fn versions_to_keep() -> usize {
+    std::env::var("NOTEMANCY_NOTE_VERSIONS")
+        .ok()
+        .and_then(|count| count.trim().parse().ok())
+        .filter(|&count| count > 0)
+        .unwrap_or(10)
+}
+
+/// The folder holding the versions of the note at `relpath`, one file per version named
+/// after when it was replaced (RFC3339, UTC).
+fn versions_dir(root: &Path, relpath: &str) -> PathBuf {
+    root.join(utils::STATE_DIR).join(HISTORY_DIR).join(relpath)
+}
+
+fn version_file(root: &Path, relpath: &str, timestamp: &str) -> PathBuf {
+    let extension = Path::new(relpath)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_string())
+        .unwrap_or_else(|| "md".to_string());
+    versions_dir(root, relpath).join(format!("{}.{}", timestamp, extension))
+}
+
+/// The timestamps of the saved versions of a note, newest first.
+pub fn list_versions(root: &Path, relpath: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(versions_dir(root, relpath)) else {
+        return Vec::new();
+    };
+    let mut timestamps: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .filter_map(|entry| {
+            entry
+                .path()
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+        })
+        .collect();
+    // RFC3339 timestamps in UTC sort chronologically as text.
+    timestamps.sort_by(|a, b| b.cmp(a));
+    timestamps
+}
+
+/// Copies the current contents of the note at `relpath` into its history before it is
+/// overwritten, then drops the oldest versions beyond `versions_to_keep`. Does nothing
+/// when the note doesn't exist yet.
+pub fn save_version(root: &Path, relpath: &str) -> std::io::Result<()> {
+    let current = root.join(relpath);
+    if !current.is_file() {
+        return Ok(());
+    }
+    let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    fs::create_dir_all(versions_dir(root, relpath))?;
+    fs::copy(&current, version_file(root, relpath, &timestamp))?;
+
+    for stale in list_versions(root, relpath).iter().skip(versions_to_keep()) {
+        fs::remove_file(version_file(root, relpath, stale))?;
+    }
+    Ok(())
+}
+
+/// Saves the previous version of a note about to be overwritten by a route, failing the
+/// request rather than losing it.
+pub fn save_version_for(vault_name: &str, relpath: &str) -> Result<(), status::Custom<String>> {
+    paths::resolve_in_vault(vault_name, relpath)?;
+    let root = paths::vault_root(vault_name)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    save_version(&root, relpath).map_err(|e| {
+        status::Custom(
+            Status::InternalServerError,
+            format!(
+                "Failed to save the previous version of '{}': {}",
+                relpath, e
+            ),
+        )
+    })
+}
+
+/// The saved versions of a note, newest first, as timestamps accepted by `/notes/restore`.
+/// A version is saved each time the note is overwritten through the API.
+#[get("/notes/history?<relpath>")]
+pub fn note_history(
+    vault: Vault,
+    relpath: String,
+) -> Result<Json<Vec<String>>, status::Custom<String>> {
+    paths::validate_relpath(&relpath).map_err(|e| status::Custom(Status::BadRequest, e))?;
+    let root = paths::vault_root(vault.name())
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    Ok(Json(list_versions(&root, &relpath)))
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RestoreRequest {
+    pub relpath: String,
+    /// The version to restore, as listed by `/notes/history`
+    pub timestamp: String,
+}
+
+/// Roll a note back to a saved version and reindex it. The contents being replaced are
+/// saved as a version first, so a restore can itself be undone.
+#[post("/notes/restore", data = "<req>")]
+pub async fn restore_version(
+    _write: WriteAccess,
+    vault: Vault,
+    req: JsonBody<RestoreRequest>,
+) -> Result<status::Custom<&'static str>, status::Custom<String>> {
+    let target = paths::resolve_in_vault(vault.name(), &req.relpath)?;
+    let root = paths::vault_root(vault.name())
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    // Only listed versions are accepted, so the timestamp can't point anywhere else.
+    if !list_versions(&root, &req.relpath).contains(&req.timestamp) {
+        return Err(status::Custom(
+            Status::NotFound,
+            format!("No version '{}' of '{}'", req.timestamp, req.relpath),
+        ));
+    }
+
+    let contents = fs::read(version_file(&root, &req.relpath, &req.timestamp))
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    save_version_for(vault.name(), &req.relpath)?;
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+    }
+    fs::write(&target, contents)
+        .map_err(|e| status::Custom(Status::InternalServerError, e.to_string()))?;
+
+    if let Err(e) = search::reindex_note_async(vault.name(), &req.relpath)
+        .await
+        .map_err(|e| e.to_string())
+    {
+        eprintln!("Failed to update search index for '{}': {}", req.relpath, e);
+    }
+    Ok(status::Custom(Status::Ok, "Note restored"))
+}
@@ -0,0 +1,385 @@
+use chrono::Local;
+use notify_debouncer_mini::notify::{RecursiveMode, Watcher};
+use notify_debouncer_mini::{DebounceEventResult, new_debouncer};
+use once_cell::sync::Lazy;
+use rocket::Shutdown;
+use rocket::http::Status;
+use rocket::response::status;
+use rocket::response::stream::{Event, EventStream};
+use rocket::serde::{Serialize, json::Json};
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::{self, error::RecvError};
+use rocket::tokio::sync::mpsc;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::paths;
+use crate::search;
+use crate::utils;
+
+/// Number of recent events kept for the status endpoint
+const RECENT_EVENTS: usize = 50;
+
+/// Whether the vault is watched for changes made outside the API, set with
+/// `NOTEMANCY_WATCH=1`.
+pub fn enabled() -> bool {
+    std::env::var("NOTEMANCY_WATCH")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// How long the watcher waits for writes to a file to settle before reporting it, from
+/// `NOTEMANCY_WATCH_DEBOUNCE_MS` (default 500).
+pub fn debounce() -> Duration {
+    let millis = std::env::var("NOTEMANCY_WATCH_DEBOUNCE_MS")
+        .ok()
+        .and_then(|millis| millis.trim().parse().ok())
+        .filter(|&millis| millis > 0)
+        .unwrap_or(500);
+    Duration::from_millis(millis)
+}
+
+#[derive(Serialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct WatchEvent {
+    /// "created", "modified" or "deleted"
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub relpath: String,
+    /// When the change was noticed (RFC3339)
+    pub at: String,
+}
+
+#[derive(Default)]
+struct WatchState {
+    /// The watched vault directory, once the watcher has started
+    watching: Option<String>,
+    /// The most recent events, oldest first
+    recent: VecDeque<WatchEvent>,
+    event_count: u64,
+}
+
+static STATE: Lazy<Mutex<WatchState>> = Lazy::new(|| Mutex::new(WatchState::default()));
+
+/// Every event, for the clients of `/notes/events`
+static EVENTS: Lazy<broadcast::Sender<WatchEvent>> =
+    Lazy::new(|| broadcast::channel(RECENT_EVENTS).0);
+
+fn record(kind: &'static str, relpath: &str) {
+    let event = WatchEvent {
+        kind,
+        relpath: relpath.to_string(),
+        at: Local::now().to_rfc3339(),
+    };
+    let mut state = STATE.lock().unwrap();
+    if state.recent.len() == RECENT_EVENTS {
+        state.recent.pop_front();
+    }
+    state.recent.push_back(event.clone());
+    state.event_count += 1;
+    // Sending only fails when nobody is listening.
+    let _ = EVENTS.send(event);
+}
+
+/// Works out how notes changed from the paths the watcher reported, given `known`, the
+/// relpaths of the notes that existed before, which is updated to match. A reported folder
+/// is compared with what is known below it, so renamed or removed folders are picked up
+/// too. Each relpath is returned once, in order.
+fn classify(
+    root: &Path,
+    known: &mut HashSet<String>,
+    changed: &[PathBuf],
+) -> Vec<(&'static str, String)> {
+    let ignore = utils::IgnoreRules::load(root);
+    let mut changes = BTreeMap::new();
+    for path in changed {
+        let relpath = paths::relpath_of(root, path);
+        if relpath.is_empty() || ignore.is_ignored(&relpath) {
+            continue;
+        }
+        let below = format!("{}/", relpath);
+        // Like the vault walk, symlinked folders aren't followed and symlinked notes only
+        // count when they point inside the vault.
+        if path.is_file() && paths::check_within(root, path).is_ok() {
+            if paths::is_note_file(path) {
+                let kind = if known.insert(relpath.clone()) {
+                    "created"
+                } else {
+                    "modified"
+                };
+                changes.entry(relpath).or_insert(kind);
+            }
+            continue;
+        }
+
+        let is_dir = path
+            .symlink_metadata()
+            .is_ok_and(|metadata| metadata.is_dir());
+        let present: HashSet<String> = if is_dir {
+            utils::note_relpaths_below(root, path)
+                .unwrap_or_default()
+                .into_iter()
+                .collect()
+        } else {
+            HashSet::new()
+        };
+        let gone: Vec<String> = known
+            .iter()
+            .filter(|k| (**k == relpath || k.starts_with(&below)) && !present.contains(*k))
+            .cloned()
+            .collect();
+        for relpath in gone {
+            known.remove(&relpath);
+            changes.entry(relpath).or_insert("deleted");
+        }
+        for relpath in present {
+            if known.insert(relpath.clone()) {
+                changes.entry(relpath).or_insert("created");
+            }
+        }
+    }
+    changes
+        .into_iter()
+        .map(|(relpath, kind)| (kind, relpath))
+        .collect()
+}
+
+/// Watches the vault for changes and keeps the search index in sync with them. Runs until
+/// the server shuts down.
+///
+/// Changes made through the API are picked up too; reindexing them again is harmless.
+pub async fn run(vault_name: String) {
+    let vault_dir = match paths::vault_root(&vault_name) {
+        Ok(vault_dir) => vault_dir,
+        Err(e) => {
+            eprintln!("Not watching vault '{}': {}", vault_name, e);
+            return;
+        }
+    };
+    let root = vault_dir.clone();
+    let known = rocket::tokio::task::spawn_blocking(move || {
+        utils::note_relpaths_below(&root, &root).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())
+    .and_then(|known| known);
+    let mut known: HashSet<String> = match known {
+        Ok(known) => known.into_iter().collect(),
+        Err(e) => {
+            eprintln!("Not watching vault '{}': {}", vault_name, e);
+            return;
+        }
+    };
+
+    // The debouncer calls back on its own thread, which is free to look at the disk.
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    let root = vault_dir.clone();
+    let debouncer = new_debouncer(debounce(), move |result: DebounceEventResult| {
+        match result {
+            Ok(events) => {
+                let changed: Vec<PathBuf> = events.into_iter().map(|event| event.path).collect();
+                for change in classify(&root, &mut known, &changed) {
+                    // Sending only fails once `run` has stopped.
+                    let _ = sender.send(change);
+                }
+            }
+            Err(e) => eprintln!("Vault watcher error: {}", e),
+        }
+    });
+    let mut debouncer = match debouncer {
+        Ok(debouncer) => debouncer,
+        Err(e) => {
+            eprintln!("Not watching vault '{}': {}", vault_name, e);
+            return;
+        }
+    };
+    if let Err(e) = debouncer
+        .watcher()
+        .watch(&vault_dir, RecursiveMode::Recursive)
+    {
+        eprintln!("Not watching vault '{}': {}", vault_name, e);
+        return;
+    }
+    STATE.lock().unwrap().watching = Some(vault_dir.to_string_lossy().to_string());
+
+    while let Some((kind, relpath)) = receiver.recv().await {
+        record(kind, &relpath);
+        let result = if kind == "deleted" {
+            search::delete_note_from_index_async(
+                &vault_name,
+                &utils::canonical_relpath(&vault_dir, &relpath),
+            )
+            .await
+            .map_err(|e| e.to_string())
+        } else {
+            search::reindex_note_async(&vault_name, &relpath)
+                .await
+                .map_err(|e| e.to_string())
+        };
+        if let Err(e) = result {
+            eprintln!("Failed to update search index for '{}': {}", relpath, e);
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct WatchStatus {
+    pub enabled: bool,
+    /// The watched vault directory (empty until the watcher has started)
+    pub watching: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_event: Option<WatchEvent>,
+    /// Number of events seen since the server started
+    pub event_count: u64,
+    /// The most recent events, newest first
+    pub recent: Vec<WatchEvent>,
+}
+
+/// Whether the vault watcher is running, and the changes it has seen recently.
+#[get("/notes/watch/status")]
+pub fn watch_status() -> Json<WatchStatus> {
+    let state = STATE.lock().unwrap();
+    Json(WatchStatus {
+        enabled: enabled(),
+        watching: state.watching.clone().unwrap_or_default(),
+        last_event: state.recent.back().cloned(),
+        event_count: state.event_count,
+        recent: state.recent.iter().rev().cloned().collect(),
+    })
+}
+
+/// Stream the vault watcher's events as server-sent events, each a JSON `WatchEvent` such
+/// as `{ "type": "modified", "relpath": "notes/foo.md", "at": "..." }`.
+///
+/// Writes to a note are debounced (see `debounce`), so a burst of them (editors often save
+/// twice) arrives as a single event. Responds with 404 unless
+/// the watcher is enabled with `NOTEMANCY_WATCH=1`.
+#[get("/notes/events")]
+pub fn note_events(mut shutdown: Shutdown) -> Result<EventStream![], status::Custom<String>> {
+    if !enabled() {
+        return Err(status::Custom(
+            Status::NotFound,
+            "The vault watcher is disabled (set NOTEMANCY_WATCH=1)".to_string(),
+        ));
+    }
+    let mut events = EVENTS.subscribe();
+    Ok(EventStream! {
+        loop {
+            let event = select! {
+                event = events.recv() => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(_)) => continue,
+                },
+                _ = &mut shutdown => break,
+            };
+            yield Event::json(&event);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn known(relpaths: &[&str]) -> HashSet<String> {
+        relpaths.iter().map(|relpath| relpath.to_string()).collect()
+    }
+
+    #[test]
+    fn note_writes_are_created_or_modified() {
+        let vault = tempfile::tempdir().unwrap();
+        let root = vault.path();
+        fs::write(root.join("old.md"), "").unwrap();
+        fs::write(root.join("new.md"), "").unwrap();
+        fs::write(root.join("image.png"), "").unwrap();
+        let mut notes = known(&["old.md"]);
+
+        let changes = classify(
+            root,
+            &mut notes,
+            &[
+                root.join("old.md"),
+                root.join("new.md"),
+                root.join("new.md"),
+                root.join("image.png"),
+            ],
+        );
+        assert_eq!(
+            changes,
+            vec![
+                ("created", "new.md".to_string()),
+                ("modified", "old.md".to_string())
+            ]
+        );
+        assert_eq!(notes, known(&["new.md", "old.md"]));
+    }
+
+    #[test]
+    fn removed_notes_and_folders_are_deleted() {
+        let vault = tempfile::tempdir().unwrap();
+        let root = vault.path();
+        let mut notes = known(&["gone.md", "projects/a.md", "projects/sub/b.md", "kept.md"]);
+
+        let changes = classify(
+            root,
+            &mut notes,
+            &[root.join("gone.md"), root.join("projects")],
+        );
+        assert_eq!(
+            changes,
+            vec![
+                ("deleted", "gone.md".to_string()),
+                ("deleted", "projects/a.md".to_string()),
+                ("deleted", "projects/sub/b.md".to_string())
+            ]
+        );
+        assert_eq!(notes, known(&["kept.md"]));
+    }
+
+    #[test]
+    fn renamed_folders_are_reconciled() {
+        let vault = tempfile::tempdir().unwrap();
+        let root = vault.path();
+        fs::create_dir_all(root.join("archive/sub")).unwrap();
+        fs::write(root.join("archive/a.md"), "").unwrap();
+        fs::write(root.join("archive/sub/b.md"), "").unwrap();
+        let mut notes = known(&["projects/a.md", "projects/sub/b.md"]);
+
+        let changes = classify(
+            root,
+            &mut notes,
+            &[root.join("projects"), root.join("archive")],
+        );
+        assert_eq!(
+            changes,
+            vec![
+                ("created", "archive/a.md".to_string()),
+                ("created", "archive/sub/b.md".to_string()),
+                ("deleted", "projects/a.md".to_string()),
+                ("deleted", "projects/sub/b.md".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn ignored_paths_are_skipped() {
+        let vault = tempfile::tempdir().unwrap();
+        let root = vault.path();
+        fs::create_dir_all(root.join(utils::STATE_DIR).join("history")).unwrap();
+        fs::write(root.join(utils::STATE_DIR).join("history/note.md"), "").unwrap();
+        let mut notes = known(&[]);
+
+        let changes = classify(
+            root,
+            &mut notes,
+            &[root.join(utils::STATE_DIR).join("history/note.md")],
+        );
+        assert!(changes.is_empty());
+        assert!(notes.is_empty());
+    }
+}